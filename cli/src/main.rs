@@ -0,0 +1,266 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use playground_common::api::{
+    self, ApiError, Asset, CompileJob, CompileRequest, CompileResponse, CreatePasteRequest,
+    CreatePasteResponse, GetPasteResponse, JobStatus,
+};
+use playground_common::bundle::{self, PageHead};
+use playground_common::encoding::Encoding;
+use reqwest::header::ACCEPT;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use tokio::fs;
+use tower_http::services::ServeDir;
+
+static PLAYGROUND_URL: &str = "https://sycamore-rs.github.io/playground";
+
+/// How often to poll a compile job that did not finish before the request timed out.
+const JOB_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Command-line client for the Sycamore playground compiler service.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// URL of the compiler service.
+    #[arg(
+        long,
+        env = "PLAYGROUND_SERVER",
+        default_value = "https://sycamore-playground.herokuapp.com"
+    )]
+    server: String,
+    /// API key that is sent to the compiler service to be rate limited per key instead of per IP
+    /// address.
+    #[arg(long, env = "PLAYGROUND_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compile a source file and write the resulting wasm and js to disk.
+    ///
+    /// Exits with status 1 if the code does not compile.
+    Compile {
+        /// The source file to compile.
+        file: PathBuf,
+        /// Directory to write `index.html`, `playground.js` and `playground_bg.wasm` to.
+        #[arg(short, long, default_value = "dist")]
+        out_dir: PathBuf,
+        /// Serve the compiled app on this port after compiling it.
+        #[arg(long, value_name = "PORT")]
+        serve: Option<u16>,
+        /// A static file that the app can fetch by its file name. Can be given multiple times.
+        #[arg(long = "asset", value_name = "FILE")]
+        assets: Vec<PathBuf>,
+    },
+    /// Upload a source file as a gist and print the link to the playground.
+    Share {
+        /// The source file to share.
+        file: PathBuf,
+    },
+    /// Fetch the code in a shared gist.
+    Fetch {
+        /// The id of the gist.
+        gist: String,
+        /// File to write the code to. Prints the code to stdout if not set.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Client for the compiler service API.
+struct Client {
+    server: String,
+    api_key: Option<String>,
+    http: reqwest::Client,
+}
+
+impl Client {
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.with_headers(self.http.get(format!("{}{path}", self.server)))
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        self.with_headers(self.http.post(format!("{}{path}", self.server)))
+    }
+
+    fn with_headers(&self, req: RequestBuilder) -> RequestBuilder {
+        let req = req.header(api::VERSION_HEADER, api::VERSION);
+        match &self.api_key {
+            Some(api_key) => req.header(api::API_KEY_HEADER, api_key),
+            None => req,
+        }
+    }
+
+    /// Sends the request and turns error responses into an [`ApiError`].
+    async fn send(&self, req: RequestBuilder) -> Result<Response> {
+        let res = req.send().await.context("could not reach the server")?;
+        if res.status().is_success() {
+            return Ok(res);
+        }
+        let status = res.status();
+        let body = res.text().await?;
+        let err = serde_json::from_str::<ApiError>(&body).unwrap_or(ApiError::Internal {
+            message: format!("{status}: {body}"),
+        });
+        Err(err.into())
+    }
+
+    async fn compile(&self, code: &str, assets: &[Asset<'_>]) -> Result<CompileResponse<'static>> {
+        let req = self
+            .post(api::COMPILE_PATH)
+            .header(ACCEPT, Encoding::Bincode.mime_type())
+            .json(&CompileRequest {
+                code: code.into(),
+                assets: assets.to_vec(),
+                // The CLI writes the default `index.html`.
+                head: PageHead::default(),
+            });
+        let mut res = self.send(req).await?;
+        if res.status() == StatusCode::ACCEPTED {
+            // The compile takes a while. Poll the job until it is finished and fetch its result.
+            let CompileJob { id } = res.json().await?;
+            eprintln!("Compiling in the background (job {id})...");
+            loop {
+                tokio::time::sleep(JOB_POLL_INTERVAL).await;
+                let req = self.get(&api::job_path(&id));
+                let status: JobStatus = self.send(req).await?.json().await?;
+                if status.is_finished() {
+                    break;
+                }
+            }
+            let req = self
+                .get(&api::job_artifact_path(&id))
+                .header(ACCEPT, Encoding::Bincode.mime_type());
+            res = self.send(req).await?;
+        }
+        let bytes = res.bytes().await?;
+        Ok(Encoding::Bincode
+            .decode::<CompileResponse>(&bytes)?
+            .into_owned())
+    }
+
+    async fn share(&self, code: &str) -> Result<String> {
+        let req = self
+            .post(api::PASTE_PATH)
+            .json(&CreatePasteRequest { code: code.into() });
+        Ok(self
+            .send(req)
+            .await?
+            .json::<CreatePasteResponse>()
+            .await?
+            .id)
+    }
+
+    async fn fetch(&self, gist: &str) -> Result<String> {
+        let req = self.get(&api::get_paste_path(gist));
+        let res = self.send(req).await?.json::<GetPasteResponse>().await?;
+        Ok(res.code.into_owned())
+    }
+}
+
+async fn read_source(file: &Path) -> Result<String> {
+    fs::read_to_string(file)
+        .await
+        .with_context(|| format!("could not read {}", file.display()))
+}
+
+/// Reads an asset. The app can fetch it by its file name.
+async fn read_asset(file: &Path) -> Result<Asset<'static>> {
+    let name = file
+        .file_name()
+        .and_then(|name| name.to_str())
+        .with_context(|| format!("invalid asset file name {}", file.display()))?;
+    let data = fs::read(file)
+        .await
+        .with_context(|| format!("could not read {}", file.display()))?;
+    Ok(Asset {
+        path: name.to_string().into(),
+        data: data.into(),
+    })
+}
+
+async fn serve(dir: PathBuf, port: u16) -> Result<()> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    eprintln!("Serving {} on http://{addr}", dir.display());
+    axum::Server::bind(&addr)
+        .serve(tower::make::Shared::new(ServeDir::new(dir)))
+        .await?;
+    Ok(())
+}
+
+async fn run(cli: Cli) -> Result<ExitCode> {
+    let client = Client {
+        server: cli.server.trim_end_matches('/').to_string(),
+        api_key: cli.api_key,
+        http: reqwest::Client::new(),
+    };
+
+    match cli.command {
+        Command::Compile {
+            file,
+            out_dir,
+            serve: port,
+            assets,
+        } => {
+            let code = read_source(&file).await?;
+            let mut asset_files = Vec::new();
+            for asset in &assets {
+                asset_files.push(read_asset(asset).await?);
+            }
+            match client.compile(&code, &asset_files).await? {
+                CompileResponse::Success {
+                    js, target, wasm, ..
+                } => {
+                    let index_html = bundle::index_html(&PageHead::default(), target);
+                    fs::create_dir_all(&out_dir).await?;
+                    fs::write(out_dir.join(bundle::INDEX_FILE_NAME), index_html).await?;
+                    fs::write(out_dir.join(bundle::JS_FILE_NAME), js.as_bytes()).await?;
+                    fs::write(out_dir.join(bundle::WASM_FILE_NAME), wasm).await?;
+                    for asset in &asset_files {
+                        fs::write(out_dir.join(&*asset.path), &asset.data).await?;
+                    }
+                    eprintln!("Wrote compiled app to {}", out_dir.display());
+                    if let Some(port) = port {
+                        serve(out_dir, port).await?;
+                    }
+                }
+                CompileResponse::CompileError(err) | CompileResponse::BundleError(err) => {
+                    eprintln!("{err}");
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+        }
+        Command::Share { file } => {
+            let code = read_source(&file).await?;
+            let id = client.share(&code).await?;
+            println!("{PLAYGROUND_URL}?gist={id}");
+        }
+        Command::Fetch { gist, output } => {
+            let code = client.fetch(&gist).await?;
+            match output {
+                Some(output) => fs::write(&output, code)
+                    .await
+                    .with_context(|| format!("could not write {}", output.display()))?,
+                None => print!("{code}"),
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::from(2)
+        }
+    }
+}
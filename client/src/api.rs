@@ -0,0 +1,96 @@
+//! Sending requests to the compiler service.
+
+use std::fmt;
+
+use gloo_net::http::{Request, Response};
+use playground_common::api::{self, ApiError};
+
+use crate::BACKEND_URL;
+
+/// An error that occurred while sending a request to the compiler service.
+#[derive(Debug)]
+pub enum RequestError {
+    /// The server responded with an error.
+    Api(ApiError),
+    /// The server could not be reached.
+    Network(gloo_net::Error),
+    /// The response could not be decoded.
+    Decode(String),
+    /// The request was not sent because its input is invalid.
+    InvalidInput(String),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Api(err) => write!(f, "{err}"),
+            Self::Network(err) => write!(f, "Could not reach the playground server: {err}"),
+            Self::Decode(err) => write!(f, "Could not decode the server response: {err}"),
+            Self::InvalidInput(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+impl From<ApiError> for RequestError {
+    fn from(err: ApiError) -> Self {
+        Self::Api(err)
+    }
+}
+
+impl From<gloo_net::Error> for RequestError {
+    fn from(err: gloo_net::Error) -> Self {
+        Self::Network(err)
+    }
+}
+
+/// Creates a `GET` request to the endpoint at `path`.
+pub fn get(path: &str) -> Request {
+    Request::get(&format!("{BACKEND_URL}{path}"))
+        .header(api::VERSION_HEADER, &api::VERSION.to_string())
+}
+
+/// Creates a `POST` request to the endpoint at `path`.
+pub fn post(path: &str) -> Request {
+    Request::post(&format!("{BACKEND_URL}{path}"))
+        .header(api::VERSION_HEADER, &api::VERSION.to_string())
+}
+
+/// Creates a `DELETE` request to the endpoint at `path`.
+pub fn delete(path: &str) -> Request {
+    Request::delete(&format!("{BACKEND_URL}{path}"))
+        .header(api::VERSION_HEADER, &api::VERSION.to_string())
+}
+
+/// Sends the request. Returns an error if the server responded with an error or if the server
+/// runs a different version of the API.
+pub async fn send(req: Request) -> Result<Response, RequestError> {
+    let res = req.send().await?;
+    let server_version = res
+        .headers()
+        .get(api::VERSION_HEADER)
+        .and_then(|version| version.parse::<u32>().ok());
+    if let Some(server) = server_version {
+        if server != api::VERSION {
+            return Err(ApiError::VersionMismatch {
+                client: api::VERSION,
+                server,
+            }
+            .into());
+        }
+    }
+
+    if !res.ok() {
+        // The request id can be used to find the failed request in the server logs.
+        if let Some(request_id) = res.headers().get(api::REQUEST_ID_HEADER) {
+            log::error!("Request {request_id} to {} failed", res.url());
+        }
+        let body = res.text().await?;
+        let err = serde_json::from_str::<ApiError>(&body).unwrap_or_else(|_| ApiError::Internal {
+            message: format!("{} {}", res.status(), res.status_text()),
+        });
+        return Err(err.into());
+    }
+    Ok(res)
+}
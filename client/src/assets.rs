@@ -0,0 +1,103 @@
+use gloo_file::futures::read_as_bytes;
+use gloo_file::File;
+use playground_common::api::Asset;
+use sycamore::futures::spawn_local_scoped;
+use sycamore::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, HtmlInputElement};
+
+use crate::toast::use_toasts;
+
+#[derive(Prop)]
+pub struct AssetListProps<'a> {
+    assets: &'a Signal<Vec<Asset<'static>>>,
+}
+
+/// Lists the static files of the project, and adds and removes them.
+#[component]
+pub fn AssetList<'a, G: Html>(cx: Scope<'a>, props: AssetListProps<'a>) -> View<G> {
+    let assets = props.assets;
+    let toasts = use_toasts(cx);
+
+    let add_files = move |ev: Event| {
+        let input: HtmlInputElement = ev.target().unwrap().unchecked_into();
+        let Some(files) = input.files() else {
+            return;
+        };
+        let files: Vec<File> = (0..files.length())
+            .filter_map(|i| files.get(i))
+            .map(File::from)
+            .collect();
+        // Allows adding the same file again after it has been removed.
+        input.set_value("");
+        spawn_local_scoped(cx, async move {
+            for file in files {
+                let data = match read_as_bytes(&file).await {
+                    Ok(data) => data,
+                    Err(err) => {
+                        toasts.error(format!("Could not read {}. {err}", file.name()));
+                        continue;
+                    }
+                };
+                let path = file.name();
+                let mut new_assets = assets.get().as_ref().clone();
+                new_assets.retain(|asset| asset.path != path);
+                new_assets.push(Asset {
+                    path: path.into(),
+                    data: data.into(),
+                });
+                assets.set(new_assets);
+            }
+        });
+    };
+
+    let list = create_memo(cx, move || {
+        View::new_fragment(
+            assets
+                .get()
+                .iter()
+                .map(|asset| {
+                    let path = asset.path.to_string();
+                    let size = format_size(asset.data.len());
+                    let remove = {
+                        let path = path.clone();
+                        move |_| {
+                            let mut new_assets = assets.get().as_ref().clone();
+                            new_assets.retain(|asset| asset.path != path);
+                            assets.set(new_assets);
+                        }
+                    };
+                    view! { cx,
+                        li(class="flex flex-row py-1") {
+                            span(class="font-mono") { (path) }
+                            span(class="grow px-2 text-gray-500") { (size) }
+                            button(type="button", on:click=remove, class="px-2 text-red-500") { "Remove" }
+                        }
+                    }
+                })
+                .collect(),
+        )
+    });
+
+    view! { cx,
+        div(class="grow p-2 overflow-auto text-sm") {
+            p(class="mb-2 text-gray-500") {
+                "Files that the app can fetch from relative URLs, e.g. "
+                code { "fetch(\"data.json\")" }
+                ". They are uploaded with the code when the app is run."
+            }
+            ul(class="divide-y divide-gray-200") {
+                (list.get().as_ref().clone())
+            }
+            input(type="file", multiple=true, class="mt-2", on:change=add_files)
+        }
+    }
+}
+
+fn format_size(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{bytes} B")
+    } else {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    }
+}
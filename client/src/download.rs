@@ -0,0 +1,30 @@
+//! Saving files that are generated in the browser.
+
+use gloo_timers::callback::Timeout;
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url};
+
+/// How long the object URL of a downloaded file is kept alive.
+const REVOKE_DELAY_MS: u32 = 10_000;
+
+/// Lets the browser save `data` as a file called `file_name`.
+pub fn download(file_name: &str, data: &[u8], mime_type: &str) -> Result<(), JsValue> {
+    let parts = Array::of1(&Uint8Array::from(data));
+    let mut options = BlobPropertyBag::new();
+    options.type_(mime_type);
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let document = web_sys::window().unwrap().document().unwrap();
+    let link: HtmlAnchorElement = document.create_element("a")?.unchecked_into();
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+    // Some browsers start the download asynchronously, so the URL has to stay valid for a while.
+    Timeout::new(REVOKE_DELAY_MS, move || {
+        let _ = Url::revoke_object_url(&url);
+    })
+    .forget();
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use sycamore::prelude::*;
+
+/// The files that can be edited in the playground.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorFile {
+    MainRs,
+    /// The contents of the `<head>` of the preview document.
+    IndexHtml,
+    StyleCss,
+    /// The static files of the project.
+    Assets,
+}
+
+impl EditorFile {
+    pub const ALL: [Self; 4] = [Self::MainRs, Self::IndexHtml, Self::StyleCss, Self::Assets];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::MainRs => "main.rs",
+            Self::IndexHtml => "index.html",
+            Self::StyleCss => "style.css",
+            Self::Assets => "assets",
+        }
+    }
+}
+
+#[derive(Prop)]
+pub struct EditorTabsProps<'a> {
+    current: &'a Signal<EditorFile>,
+    tailwind: &'a Signal<bool>,
+}
+
+/// Switches between the files, and toggles Tailwind.
+#[component]
+pub fn EditorTabs<'a, G: Html>(cx: Scope<'a>, props: EditorTabsProps<'a>) -> View<G> {
+    let current = props.current;
+    let tabs = View::new_fragment(
+        EditorFile::ALL
+            .into_iter()
+            .map(|file| {
+                view! { cx,
+                    button(
+                        type="button",
+                        on:click=move |_| current.set(file),
+                        class=if *current.get() == file {
+                            "px-3 py-1 border-b-2 border-orange-400"
+                        } else {
+                            "px-3 py-1 border-b-2 border-transparent text-gray-500"
+                        }
+                    ) { (file.file_name()) }
+                }
+            })
+            .collect(),
+    );
+
+    view! { cx,
+        div(class="flex flex-row text-sm border-b border-gray-300") {
+            (tabs)
+            div(class="grow")
+            label(class="px-3 py-1", title="Load the Tailwind Play CDN, which generates styles for Tailwind classes and processes style.css in the browser. Not meant for production.") {
+                input(type="checkbox", class="mr-1", bind:checked=props.tailwind)
+                "Tailwind"
+            }
+        }
+    }
+}
@@ -1,22 +1,52 @@
+mod api;
+mod assets;
+mod download;
+mod editor_tabs;
 mod editor_view;
 mod pastebin;
+mod preview;
+mod repo;
+mod share_modal;
+mod test_results;
+mod toast;
+mod url_share;
 
-use std::error::Error;
-
-use gloo_net::http::Request;
+use gloo_file::futures::read_as_bytes;
+use gloo_file::File;
 use gloo_storage::{LocalStorage, Storage};
-use js_sys::Uint8Array;
+use gloo_timers::future::TimeoutFuture;
+use js_sys::{Array, Uint8Array};
 use pastebin::new_paste;
-use playground_common::{CompileRequest, CompileResponse};
+use playground_common::api::{
+    asset_path, job_artifact_path, job_path, Asset, CompileJob, CompileRequest, CompileResponse,
+    ImportedProject, JobStatus, JsTarget, TestResponse, TestResult, JOBS_PATH,
+};
+use playground_common::bundle::{Bundle, PageHead};
+use playground_common::encoding::Encoding;
+use playground_common::project::Project;
+use playground_common::DEFAULT_CODE;
 use sycamore::futures::spawn_local_scoped;
 use sycamore::prelude::*;
 use sycamore::suspense::Suspense;
 use wasm_bindgen::prelude::Closure;
 use wasm_bindgen::{JsCast, JsValue};
-use web_sys::{HtmlDocument, HtmlIFrameElement, UrlSearchParams};
+use web_sys::{
+    AbortController, AbortSignal, Blob, BlobPropertyBag, Event, HtmlDocument, HtmlIFrameElement,
+    HtmlInputElement, Url, UrlSearchParams,
+};
 
+use crate::api::RequestError;
+use crate::assets::AssetList;
+use crate::download::download;
+use crate::editor_tabs::{EditorFile, EditorTabs};
 use crate::editor_view::EditorView;
 use crate::pastebin::get_paste;
+use crate::preview::preview_document;
+use crate::repo::{import_repo, import_zip, open_imported_project, take_imported_project};
+use crate::share_modal::ShareModal;
+use crate::test_results::{run_tests, TestResultList};
+use crate::toast::{use_toasts, ToastContainer, Toasts};
+use crate::url_share::{decode_code, encode_code, MAX_ENCODED_LEN};
 
 static BACKEND_URL: &str = if cfg!(debug_assertions) {
     "http://localhost:3000"
@@ -24,43 +54,91 @@ static BACKEND_URL: &str = if cfg!(debug_assertions) {
     "https://sycamore-playground.herokuapp.com"
 };
 
-static DEFAULT_EDITOR_CODE: &str = r#"use sycamore::prelude::*;
-
-fn main() {
-    console_error_panic_hook::set_once();
-    console_log::init_with_level(log::Level::Debug).unwrap();
-
-    sycamore::render(|cx|
-        view! { cx, "Hello World!" }
-    );
-}
-"#;
+static PLAYGROUND_URL: &str = "https://sycamore-rs.github.io/playground";
 
 #[derive(Prop)]
-struct NavBarProps<'a, F: FnMut() + 'a> {
+struct NavBarProps<
+    'a,
+    F: FnMut() + 'a,
+    T: FnMut() + 'a,
+    S: FnMut() + 'a,
+    D: FnMut() + 'a,
+    E: FnMut() + 'a,
+> {
     run: F,
+    test: T,
+    stop: S,
+    download: D,
+    export: E,
     building: &'a ReadSignal<bool>,
+    can_download: &'a ReadSignal<bool>,
     source: &'a ReadSignal<String>,
 }
 
 #[component]
-fn NavBar<'a, G: Html>(cx: Scope<'a>, mut props: NavBarProps<'a, impl FnMut()>) -> View<G> {
+fn NavBar<'a, G: Html>(
+    cx: Scope<'a>,
+    mut props: NavBarProps<
+        'a,
+        impl FnMut(),
+        impl FnMut(),
+        impl FnMut(),
+        impl FnMut(),
+        impl FnMut(),
+    >,
+) -> View<G> {
+    let toasts = use_toasts(cx);
     let share_modal_open = create_signal(cx, false);
-    let share_gist_id = create_signal(cx, String::new());
+    let share_gist_id = create_signal(cx, None::<String>);
     let share_pastebin_url = share_gist_id.map(cx, |id| {
-        format!("https://gist.github.com/sycamore-playground/{id}")
-    });
-    let share_playground_url = share_gist_id.map(cx, |id| {
-        format!("https://sycamore-rs.github.io/playground?gist={id}")
+        id.as_ref()
+            .map(|id| format!("https://gist.github.com/sycamore-playground/{id}"))
     });
+    let share_playground_url = create_signal(cx, String::new());
     let share = move |_| {
+        // Small snippets are encoded directly in the URL. Only fall back to creating a gist if
+        // the resulting URL would be too long.
+        let encoded = encode_code(&props.source.get());
+        if encoded.len() <= MAX_ENCODED_LEN {
+            share_gist_id.set(None);
+            share_playground_url.set(format!("{PLAYGROUND_URL}?code={encoded}"));
+            share_modal_open.set(true);
+            return;
+        }
+        toasts
+            .info("This snippet is too large to share as a link. Uploading it as a gist instead.");
         spawn_local_scoped(cx, async {
-            let id = new_paste(&props.source.get())
-                .await
-                .expect("could not upload code snippet to gist");
+            let id = match new_paste(&props.source.get()).await {
+                Ok(id) => id,
+                Err(err) => {
+                    log::error!("Could not upload code snippet to gist: {err}");
+                    toasts.error(format!("Could not share code snippet. {err}"));
+                    return;
+                }
+            };
             log::info!("Generated gist with id: {id}");
+            share_playground_url.set(format!("{PLAYGROUND_URL}?gist={id}"));
             share_modal_open.set(true);
-            share_gist_id.set(id.to_string());
+            share_gist_id.set(Some(id));
+        });
+    };
+
+    let import = move |ev: Event| {
+        let input: HtmlInputElement = ev.target().unwrap().unchecked_into();
+        let Some(file) = input.files().and_then(|files| files.get(0)).map(File::from) else {
+            return;
+        };
+        // Allows importing the same file again.
+        input.set_value("");
+        spawn_local_scoped(cx, async move {
+            let name = file.name();
+            let res = match read_as_bytes(&file).await {
+                Ok(zip) => import_zip(&zip).await.map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+            if let Err(err) = res.and_then(|project| open_imported_project(&name, &project)) {
+                toasts.error(format!("Could not import {name}. {err}"));
+            }
         });
     };
 
@@ -78,69 +156,291 @@ fn NavBar<'a, G: Html>(cx: Scope<'a>, mut props: NavBarProps<'a, impl FnMut()>)
                 disabled=*props.building.get(),
                 class="px-5 my-1 ml-10 bg-green-400 font-bold text-white disabled:bg-green-200 rounded shadow-inner"
             ) { "Run" }
+            button(
+                type="button",
+                on:click=move |_| (props.test)(),
+                disabled=*props.building.get(),
+                title="Run the #[test] and #[wasm_bindgen_test] functions in the code",
+                class="px-5 my-1 ml-2 bg-teal-400 font-bold text-white disabled:bg-teal-200 rounded shadow-inner"
+            ) { "Test" }
+            button(
+                type="button",
+                on:click=move |_| (props.stop)(),
+                class=if *props.building.get() {
+                    "px-5 my-1 ml-2 bg-red-400 font-bold text-white rounded shadow-inner"
+                } else {
+                    "hidden"
+                }
+            ) { "Stop" }
             div(class="grow")
+            label(
+                title="Import a project that builds with trunk from a zip archive",
+                class="flex items-center px-5 my-1 mr-2 bg-gray-400 font-bold text-white rounded shadow-inner cursor-pointer"
+            ) {
+                "Import"
+                input(type="file", accept=".zip,application/zip", class="hidden", on:change=import)
+            }
+            button(
+                type="button",
+                on:click=move |_| (props.download)(),
+                disabled=!*props.can_download.get(),
+                title="Download the last build as a zip that can be hosted statically",
+                class="px-5 my-1 mr-2 bg-blue-400 font-bold text-white disabled:bg-blue-200 rounded shadow-inner"
+            ) { "Download" }
+            button(
+                type="button",
+                on:click=move |_| (props.export)(),
+                title="Download the code as a project that builds with trunk",
+                class="px-5 my-1 mr-2 bg-purple-400 font-bold text-white rounded shadow-inner"
+            ) { "Export" }
             button(
                 type="button",
                 on:click=share,
                 class="px-5 my-1 mr-5 bg-yellow-400 font-bold text-white rounded shadow-inner"
             ) { "Share" }
         }
-        // Background dim.
-        div(class=format!("fixed inset-0 w-full h-full z-40 bg-gray-500 bg-opacity-75 transition-opacity {}", if *share_modal_open.get() { "" } else { "hidden" }))
-        // Share modal.
-        div(
-            class=format!("fixed inset-0 w-full z-50 {}", if *share_modal_open.get() { "" } else { "hidden" }),
-            role="dialog",
-            aria-modal=true,
-        ) {
-            // Modal content.
-            div(class="bg-white container mx-auto mt-5 px-5 py-3 rounded shadow-lg") {
-                h1(class="text-xl font-bold") { "Share" }
-                p { "GitHub Gist: "
-                    a(class="text-blue-600 underline", href=share_pastebin_url.get()) { (share_pastebin_url.get()) }
-                }
-                p { "Runnable playground: "
-                    a(class="text-blue-600 underline", href=share_playground_url.get()) { (share_playground_url.get()) }
-                }
-                button(type="button", class="px-5 bg-yellow-400 font-bold text-white rounded shadow-inner", on:click=|_| share_modal_open.set(false)) { "Done" }
-            }
-        }
+        ShareModal(open=share_modal_open, gist_url=share_pastebin_url, playground_url=share_playground_url)
     }
 }
 
-async fn send_compile_req(code: &str) -> Result<CompileResponse<'_>, Box<dyn Error>> {
-    let bytes = Request::post(&format!("{BACKEND_URL}/compile"))
-        .json(&CompileRequest { code: code.into() })?
-        .send()
-        .await?
-        .binary()
-        .await?;
+/// How often to poll a running compile job.
+const JOB_POLL_INTERVAL_MS: u32 = 500;
+
+/// Starts a compile job on the server and returns its id.
+async fn start_compile_job(
+    code: &str,
+    assets: &[Asset<'_>],
+    head: &PageHead,
+) -> Result<String, RequestError> {
+    let req = api::post(JOBS_PATH).json(&CompileRequest {
+        code: code.into(),
+        assets: assets.to_vec(),
+        head: head.clone(),
+    })?;
+    let CompileJob { id } = api::send(req).await?.json().await?;
+    Ok(id)
+}
+
+/// Polls the compile job until it is finished and fetches its result. All requests are aborted
+/// when `signal` is.
+async fn wait_for_compile_job(
+    id: &str,
+    signal: &AbortSignal,
+) -> Result<CompileResponse<'static>, RequestError> {
+    loop {
+        TimeoutFuture::new(JOB_POLL_INTERVAL_MS).await;
+        let req = api::get(&job_path(id)).abort_signal(Some(signal));
+        let status: JobStatus = api::send(req).await?.json().await?;
+        if status.is_finished() {
+            break;
+        }
+    }
+    let req = api::get(&job_artifact_path(id))
+        .header("Accept", Encoding::Bincode.mime_type())
+        .abort_signal(Some(signal));
+    let bytes = api::send(req).await?.binary().await?;
     // Deserialize into a `CompileResponse`.
-    Ok(bincode::deserialize(&bytes)?)
+    Encoding::Bincode
+        .decode::<CompileResponse>(&bytes)
+        .map(CompileResponse::into_owned)
+        .map_err(|err| RequestError::Decode(err.to_string()))
+}
+
+/// Cancels the compile job, which kills the build on the server.
+async fn cancel_compile_job(id: &str) -> Result<(), RequestError> {
+    api::send(api::delete(&job_path(id))).await?;
+    Ok(())
+}
+
+/// Creates an object URL for the JS glue code. The URL is also valid in the preview iframe, which
+/// has the same origin.
+fn js_blob_url(js: &str) -> String {
+    let parts = Array::of1(&JsValue::from_str(js));
+    let mut options = BlobPropertyBag::new();
+    options.type_("text/javascript");
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &options).unwrap();
+    Url::create_object_url_with_blob(&blob).unwrap()
+}
+
+/// A successful build, with the files that it was run with.
+struct Build {
+    js: String,
+    target: JsTarget,
+    wasm: Vec<u8>,
+    assets: Vec<Asset<'static>>,
+    head: PageHead,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Preview {
     Initial,
     Building,
+    Testing,
     ShowIFrame,
-    ShowCompileError { err: String },
-    ShowOtherError { err: String },
+    ShowTestResults {
+        results: Vec<TestResult>,
+        output: String,
+    },
+    ShowCompileError {
+        err: String,
+    },
+    ShowBundleError {
+        err: String,
+    },
+    ShowTestError {
+        err: String,
+    },
+    ShowOtherError {
+        err: String,
+    },
+}
+
+impl Preview {
+    /// Whether a build or a test run is in progress.
+    fn is_busy(&self) -> bool {
+        matches!(self, Preview::Building | Preview::Testing)
+    }
+}
+
+/// The files that the playground starts with.
+struct InitialFiles {
+    code: String,
+    head: PageHead,
+    assets: Vec<Asset<'static>>,
+    /// Whether the files were opened from a link, rather than restored from local storage or
+    /// imported by the user.
+    shared: bool,
 }
 
 #[component]
-fn Index<G: Html>(cx: Scope, initial_code: String) -> View<G> {
+fn Index<G: Html>(cx: Scope, initial: InitialFiles) -> View<G> {
+    // Files opened from a link are only saved to local storage once they are edited, so that
+    // opening a link does not replace the files that the recipient has saved.
+    let persist = create_signal(cx, !initial.shared);
+    let shared_files = initial.shared.then(|| {
+        (
+            initial.code.clone(),
+            initial.head.clone(),
+            initial.assets.clone(),
+        )
+    });
     let preview = create_signal(cx, Preview::Initial);
-    let source = create_rc_signal(initial_code);
+    let source = create_rc_signal(initial.code);
     let source_ref = create_ref(cx, source.clone());
     let iframe_ref = create_node_ref(cx);
+    let current_file = create_signal(cx, EditorFile::MainRs);
+    let html_head = create_signal(cx, initial.head.html);
+    let css = create_signal(cx, initial.head.css);
+    let tailwind = create_signal(cx, initial.head.tailwind);
+    let assets = create_signal(cx, initial.assets);
+
+    let current_head = move || PageHead {
+        html: html_head.get().as_ref().clone(),
+        css: css.get().as_ref().clone(),
+        tailwind: *tailwind.get(),
+    };
+
+    let toasts = use_toasts(cx);
+    // Aborts the requests of the build or test run that is in progress.
+    let abort_controller = create_signal(cx, None::<AbortController>);
+    // The id of the compile job that is in progress, once the server has started it.
+    let job_id = create_signal(cx, None::<String>);
+    // The build that is shown in the preview, for downloading it.
+    let last_build = create_signal(cx, None::<Build>);
+
+    let stop = move || {
+        if let Some(controller) = abort_controller.get().as_ref() {
+            controller.abort();
+        }
+        abort_controller.set(None);
+        // If the job has not been started yet, `run` cancels it as soon as it has been.
+        if let Some(id) = job_id.get().as_ref().clone() {
+            spawn_local_scoped(cx, async move {
+                if let Err(err) = cancel_compile_job(&id).await {
+                    toasts.error(format!("Could not stop the build. {err}"));
+                }
+            });
+        }
+        job_id.set(None);
+        preview.set(Preview::Initial);
+    };
+
+    let download_build = move || {
+        let last_build = last_build.get();
+        let Some(build) = last_build.as_ref() else {
+            return;
+        };
+        let bundle = Bundle {
+            js: &build.js,
+            target: build.target,
+            wasm: &build.wasm,
+            assets: &build.assets,
+            head: &build.head,
+        };
+        let res = bundle
+            .to_zip()
+            .map_err(|err| err.to_string())
+            .and_then(|zip| {
+                download("playground.zip", &zip, "application/zip")
+                    .map_err(|err| format!("{err:?}"))
+            });
+        if let Err(err) = res {
+            toasts.error(format!("Could not download the build. {err}"));
+        }
+    };
+
+    let export_project = move || {
+        let project = Project {
+            code: &source_ref.get(),
+            assets: &assets.get(),
+            head: &current_head(),
+        };
+        let res = project
+            .to_zip()
+            .map_err(|err| err.to_string())
+            .and_then(|zip| {
+                download("playground-project.zip", &zip, "application/zip")
+                    .map_err(|err| format!("{err:?}"))
+            });
+        if let Err(err) = res {
+            toasts.error(format!("Could not export the project. {err}"));
+        }
+    };
 
     let run = move || {
         spawn_local_scoped(cx, async move {
-            if *preview.get() != Preview::Building {
+            if !preview.get().is_busy() {
                 preview.set(Preview::Building);
+                last_build.set(None);
+                let controller = AbortController::new().unwrap();
+                let signal = controller.signal();
+                abort_controller.set(Some(controller));
+
                 let code = source_ref.get();
-                let res = match send_compile_req(&code).await {
+                // The build belongs to the files that it was started with.
+                let build_assets = assets.get();
+                let head = current_head();
+                let res = match start_compile_job(&code, &build_assets, &head).await {
+                    Ok(id) if signal.aborted() => {
+                        // The build was stopped while the job was being started.
+                        if let Err(err) = cancel_compile_job(&id).await {
+                            toasts.error(format!("Could not stop the build. {err}"));
+                        }
+                        return;
+                    }
+                    Ok(id) => {
+                        job_id.set(Some(id.clone()));
+                        wait_for_compile_job(&id, &signal).await
+                    }
+                    Err(err) => Err(err),
+                };
+                if signal.aborted() {
+                    // The build was stopped. Its requests fail with an abort error.
+                    return;
+                }
+                abort_controller.set(None);
+                job_id.set(None);
+                let res = match res {
                     Ok(res) => res,
                     Err(err) => {
                         preview.set(Preview::ShowOtherError {
@@ -151,24 +451,17 @@ fn Index<G: Html>(cx: Scope, initial_code: String) -> View<G> {
                 };
 
                 match res {
-                    CompileResponse::Success { js, wasm } => {
+                    CompileResponse::Success {
+                        js,
+                        target,
+                        wasm,
+                        code_hash,
+                    } => {
                         preview.set(Preview::ShowIFrame);
                         // Update iframe.
-                        let iframe_src = format!(
-                            r#"<!DOCTYPE html>
-                        <html>
-                            <head>
-                                <meta content="text/html;charset=utf-8" http-equiv="Content-Type" />
-                                <script type="module">
-                                    {js}
-                                    window.init = init;
-                                </script>
-                            </head>
-                            <body>
-                                <noscript>You need to enable Javascript to run this interactive app.</noscript>
-                            </body>
-                        </html>"#
-                        );
+                        let js_url = js_blob_url(&js);
+                        let assets_url = format!("{BACKEND_URL}{}", asset_path(&code_hash, ""));
+                        let iframe_src = preview_document(&head, &js_url, target, &assets_url);
                         let window = iframe_ref
                             .get::<DomNode>()
                             .unchecked_into::<HtmlIFrameElement>()
@@ -184,39 +477,149 @@ fn Index<G: Html>(cx: Scope, initial_code: String) -> View<G> {
                                 let init = js_sys::Reflect::get(&window, &"init".into()).unwrap();
                                 let init: js_sys::Function = init.unchecked_into();
                                 init.call1(&window, &buf.into()).unwrap();
+                                Url::revoke_object_url(&js_url).unwrap();
                             })
                             .unchecked_into(),
                         ));
+                        last_build.set(Some(Build {
+                            js: js.into_owned(),
+                            target,
+                            wasm: wasm.into_owned(),
+                            assets: build_assets.as_ref().clone(),
+                            head,
+                        }));
                     }
                     CompileResponse::CompileError(err) => {
                         preview.set(Preview::ShowCompileError { err });
                     }
+                    CompileResponse::BundleError(err) => {
+                        preview.set(Preview::ShowBundleError { err });
+                    }
                 };
             }
         });
     };
 
+    let test = move || {
+        spawn_local_scoped(cx, async move {
+            if preview.get().is_busy() {
+                return;
+            }
+            preview.set(Preview::Testing);
+            let controller = AbortController::new().unwrap();
+            let signal = controller.signal();
+            abort_controller.set(Some(controller));
+
+            let res = run_tests(&source_ref.get(), &signal).await;
+            if signal.aborted() {
+                // The test run was stopped. Its request fails with an abort error.
+                return;
+            }
+            abort_controller.set(None);
+            preview.set(match res {
+                Ok(TestResponse::Finished { results, output }) => {
+                    Preview::ShowTestResults { results, output }
+                }
+                Ok(TestResponse::CompileError(err)) => Preview::ShowCompileError { err },
+                Ok(TestResponse::RunError(err)) => Preview::ShowTestError { err },
+                Err(err) => Preview::ShowOtherError {
+                    err: err.to_string(),
+                },
+            });
+        });
+    };
+
+    if let Some((code, head, shared_assets)) = shared_files {
+        create_effect(cx, move || {
+            let edited = *source_ref.get() != code
+                || current_head() != head
+                || *assets.get() != shared_assets;
+            if edited && !*persist.get_untracked() {
+                persist.set(true);
+            }
+        });
+    }
     // Save changes to code to local storage.
     create_effect(cx, || {
+        if !*persist.get() {
+            return;
+        }
         LocalStorage::set("CODE", source_ref.get().as_ref())
             .expect("failed to save code to local storage");
     });
+    create_effect(cx, || {
+        if !*persist.get() {
+            return;
+        }
+        LocalStorage::set("HTML_HEAD", html_head.get().as_ref())
+            .expect("failed to save index.html to local storage");
+        LocalStorage::set("CSS", css.get().as_ref())
+            .expect("failed to save style.css to local storage");
+        LocalStorage::set("TAILWIND", *tailwind.get())
+            .expect("failed to save settings to local storage");
+    });
+    create_effect(cx, || {
+        if !*persist.get() {
+            return;
+        }
+        // Large assets may not fit into local storage. They are kept for the session anyway.
+        if let Err(err) = LocalStorage::set("ASSETS", assets.get().as_ref()) {
+            log::warn!("Could not save assets to local storage: {err}");
+        }
+    });
 
     view! { cx,
-        NavBar(run=run, building=preview.map(cx, |p| p == &Preview::Building), source=source_ref)
+        NavBar(
+            run=run,
+            test=test,
+            stop=stop,
+            download=download_build,
+            export=export_project,
+            building=preview.map(cx, Preview::is_busy),
+            can_download=last_build.map(cx, |build| build.is_some()),
+            source=source_ref
+        )
         main(
             class="px-2 top-10 bottom-0 w-full absolute \
                 grid grid-cols-1 grid-rows-2 md:grid-cols-2 md:grid-rows-1 \
                 divide-y md:divide-y-0 md:divide-x divide-gray-400 space-y-2 md:space-x-2 \
                 overflow-hidden"
         ) {
-            EditorView(source=source)
+            div(class="flex flex-col h-full overflow-hidden") {
+                EditorTabs(current=current_file, tailwind=tailwind)
+                // The editor stays mounted so that it keeps its state while another file is open.
+                div(class=if *current_file.get() == EditorFile::MainRs { "grow min-h-0" } else { "hidden" }) {
+                    EditorView(source=source)
+                }
+                (match *current_file.get() {
+                    EditorFile::MainRs => view! { cx, },
+                    EditorFile::IndexHtml => view! { cx,
+                        textarea(
+                            class="grow p-1 font-mono text-sm outline-none resize-none",
+                            spellcheck="false",
+                            placeholder="<!-- Added to the <head> of the preview, e.g. <link> and <meta> tags. -->",
+                            bind:value=html_head
+                        )
+                    },
+                    EditorFile::StyleCss => view! { cx,
+                        textarea(
+                            class="grow p-1 font-mono text-sm outline-none resize-none",
+                            spellcheck="false",
+                            placeholder="/* Styles for the preview. */",
+                            bind:value=css
+                        )
+                    },
+                    EditorFile::Assets => view! { cx,
+                        AssetList(assets=assets)
+                    },
+                })
+            }
             div(class="block h-full w-full pb-2 overflow-auto") {
                 (match preview.get().as_ref().clone() {
                     Preview::Initial => view! { cx,
                         div {
                             p {
-                                "Press run to preview the app."
+                                "Press run to preview the app, or test to run its tests."
                             }
                         }
                     },
@@ -227,9 +630,19 @@ fn Index<G: Html>(cx: Scope, initial_code: String) -> View<G> {
                             }
                         }
                     },
+                    Preview::Testing => view! { cx,
+                        div {
+                            p {
+                                "Running tests..."
+                            }
+                        }
+                    },
                     Preview::ShowIFrame => view! { cx,
                         iframe(class="h-full w-full", title="preview", ref=iframe_ref)
                     },
+                    Preview::ShowTestResults { results, output } => view! { cx,
+                        TestResultList(results=results, output=output)
+                    },
                     Preview::ShowCompileError { err } => view! { cx,
                         div {
                             p {
@@ -238,6 +651,22 @@ fn Index<G: Html>(cx: Scope, initial_code: String) -> View<G> {
                             pre { (err) }
                         }
                     },
+                    Preview::ShowBundleError { err } => view! { cx,
+                        div {
+                            p {
+                                "Bundling error."
+                            }
+                            pre { (err) }
+                        }
+                    },
+                    Preview::ShowTestError { err } => view! { cx,
+                        div {
+                            p {
+                                "Test error."
+                            }
+                            pre { (err) }
+                        }
+                    },
                     Preview::ShowOtherError { err } => view! { cx,
                         div {
                             p {
@@ -252,33 +681,107 @@ fn Index<G: Html>(cx: Scope, initial_code: String) -> View<G> {
     }
 }
 
+/// Shows what could not be imported from the project `name`.
+fn report_import(toasts: &Toasts, name: &str, project: &ImportedProject) {
+    if !project.unsupported_dependencies.is_empty() {
+        toasts.error(format!(
+            "{name} depends on crates that are not available in the playground: {}.",
+            project.unsupported_dependencies.join(", ")
+        ));
+    }
+    for warning in &project.warnings {
+        toasts.info(format!("Imported {name} partially: {warning}."));
+    }
+}
+
 #[component]
 async fn App<G: Html>(cx: Scope<'_>) -> View<G> {
     // If we have a paste id in the query parameter, get the code from the pastebin.
     let url_params =
         UrlSearchParams::new_with_str(&web_sys::window().unwrap().location().search().unwrap())
             .unwrap();
-    let initial_code = if let Some(gist_id) = url_params.get("gist") {
-        let url = format!("{BACKEND_URL}/paste/{gist_id}");
-        log::info!("Loading gist from {url}");
-        get_paste(&url)
-            .await
-            .expect("could not fetch from pastebin")
+    let toasts = use_toasts(cx);
+    // We get the files before writing the new files to local storage in the effects of `Index`.
+    let mut head = PageHead {
+        html: LocalStorage::get("HTML_HEAD").unwrap_or_else(|_| String::new()),
+        css: LocalStorage::get("CSS").unwrap_or_else(|_| String::new()),
+        tailwind: LocalStorage::get("TAILWIND").unwrap_or(false),
+    };
+    let mut assets = LocalStorage::get::<Vec<Asset>>("ASSETS").unwrap_or_else(|_| Vec::new());
+    // Shares only contain the code. The recipient sees it with the default page rather than with
+    // their own head, styles and assets, which the shared code was not written for.
+    let mut reset_files = || {
+        head = PageHead::default();
+        assets = Vec::new();
+    };
+    // Imported zip archives are the user's own files, unlike the ones opened from a link.
+    let mut from_link = true;
+    let shared_code = if let Some((name, project)) = take_imported_project() {
+        from_link = false;
+        report_import(toasts, &name, &project);
+        head = project.head;
+        assets = project.assets;
+        Some(project.code.into_owned())
+    } else if let Some(gist_id) = url_params.get("gist") {
+        log::info!("Loading gist {gist_id}");
+        match get_paste(&gist_id).await {
+            Ok(code) => {
+                reset_files();
+                Some(code)
+            }
+            Err(err) => {
+                toasts.error(format!("Could not load gist {gist_id}. {err}"));
+                None
+            }
+        }
+    } else if let Some(repo) = url_params.get("repo") {
+        log::info!("Importing repository {repo}");
+        match import_repo(&repo).await {
+            Ok(project) => {
+                report_import(toasts, &repo, &project);
+                head = project.head;
+                assets = project.assets;
+                Some(project.code.into_owned())
+            }
+            Err(err) => {
+                toasts.error(format!("Could not import repository {repo}. {err}"));
+                None
+            }
+        }
+    } else if let Some(encoded) = url_params.get("code") {
+        match decode_code(&encoded) {
+            Ok(code) => {
+                reset_files();
+                Some(code)
+            }
+            Err(err) => {
+                toasts.error(format!("Could not load code from URL. {err}"));
+                None
+            }
+        }
     } else if let Some(_example_name) = url_params.get("example") {
         todo!("fetch example from github")
     } else {
+        None
+    };
+    let shared = from_link && shared_code.is_some();
+    let initial_code = shared_code.unwrap_or_else(|| {
         // Get saved code from local storage or initialize with default code.
-        // We get the code before writing the new code to local storage in the effect below.
         let storage: String = LocalStorage::get("CODE").unwrap_or_else(|_| String::new());
         if storage.trim() == "" {
-            DEFAULT_EDITOR_CODE.to_string()
+            DEFAULT_CODE.to_string()
         } else {
             storage
         }
-    };
+    });
 
     view! { cx,
-        Index(initial_code)
+        Index(InitialFiles {
+            code: initial_code,
+            head,
+            assets,
+            shared,
+        })
     }
 }
 
@@ -286,9 +789,13 @@ fn main() {
     console_error_panic_hook::set_once();
     console_log::init_with_level(log::Level::Debug).unwrap();
 
-    sycamore::render(|cx| view! { cx, 
-        Suspense(fallback=view!{ cx, "Loading..." }) {
-            App {}
+    sycamore::render(|cx| {
+        provide_context(cx, Toasts::default());
+        view! { cx,
+            Suspense(fallback=view!{ cx, "Loading..." }) {
+                App {}
+            }
+            ToastContainer {}
         }
     });
 }
@@ -1,22 +1,16 @@
-use std::error::Error;
+use playground_common::api::{self, CreatePasteRequest, CreatePasteResponse, GetPasteResponse};
 
-use gloo_net::http::Request;
-use js_sys::encode_uri_component;
+use crate::api::{get, post, send, RequestError};
 
-use crate::BACKEND_URL;
-
-/// Creates a new paste on <https://pastebin.com>. Returns the id of the created paste.
-pub async fn new_paste(code: &str) -> Result<String, Box<dyn Error>> {
-    let encoded = encode_uri_component(code);
-    Ok(Request::post(&format!("{BACKEND_URL}/paste"))
-        .body(format!("code={encoded}"))
-        .header("Content-Type", "application/x-www-form-urlencoded")
-        .send()
-        .await?
-        .text()
-        .await?)
+/// Creates a new gist through the backend. Returns the id of the created gist.
+pub async fn new_paste(code: &str) -> Result<String, RequestError> {
+    let req = post(api::PASTE_PATH).json(&CreatePasteRequest { code: code.into() })?;
+    let res = send(req).await?;
+    Ok(res.json::<CreatePasteResponse>().await?.id)
 }
 
-pub async fn get_paste(url: &str) -> Result<String, Box<dyn Error>> {
-    Ok(Request::get(url).send().await?.text().await?)
+/// Fetches the code in the gist with the given id through the backend.
+pub async fn get_paste(id: &str) -> Result<String, RequestError> {
+    let res = send(get(&api::get_paste_path(id))).await?;
+    Ok(res.json::<GetPasteResponse>().await?.code.into_owned())
 }
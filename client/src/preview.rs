@@ -0,0 +1,30 @@
+//! The document that is shown in the preview iframe.
+
+use playground_common::api::JsTarget;
+use playground_common::bundle::{document, PageHead};
+
+/// Builds the preview document. The JS glue code is loaded from `js_url`, which has to be a
+/// same-origin URL. Relative URLs are resolved against `assets_url`. The wasm module is not
+/// initialized; the document stores the init function in `window.init` instead.
+pub fn preview_document(
+    head: &PageHead,
+    js_url: &str,
+    target: JsTarget,
+    assets_url: &str,
+) -> String {
+    // Loading the glue code from a URL means that the init function can be found regardless of
+    // its name in the generated code.
+    let load_js = match target {
+        JsTarget::Web => format!(
+            r#"<script type="module">
+            import init from "{js_url}";
+            window.init = init;
+        </script>"#
+        ),
+        JsTarget::NoModules => format!(
+            r#"<script src="{js_url}"></script>
+        <script>window.init = wasm_bindgen;</script>"#
+        ),
+    };
+    document(head, Some(assets_url), &load_js)
+}
@@ -0,0 +1,113 @@
+use gloo_storage::{SessionStorage, Storage};
+use js_sys::Uint8Array;
+use playground_common::api::{self, ImportedProject};
+
+use crate::api::{get, post, send, RequestError};
+
+/// The key in session storage of a project that was imported from a zip archive.
+const IMPORTED_PROJECT_KEY: &str = "IMPORTED_PROJECT";
+
+/// Imports a project from a GitHub repository through the backend. `repo` is `owner/name`,
+/// optionally followed by the path of the project in the repository.
+pub async fn import_repo(repo: &str) -> Result<ImportedProject<'static>, RequestError> {
+    let repo = encode_repo(repo).ok_or_else(|| {
+        RequestError::InvalidInput(
+            "Expected `owner/name`, optionally followed by the path of the project.".to_string(),
+        )
+    })?;
+    let res = send(get(&api::repo_path(&repo))).await?;
+    Ok(res.json::<ImportedProject>().await?)
+}
+
+/// Percent-encodes every segment of a repository path, or returns `None` if it is not one. The
+/// owner and the name may only contain the characters that GitHub allows, and there must not be
+/// empty, `.` or `..` segments, which the browser would resolve even if they are encoded.
+fn encode_repo(repo: &str) -> Option<String> {
+    let segments: Vec<_> = repo.trim_matches('/').split('/').collect();
+    let is_valid_name = |s: &str| {
+        s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+    };
+    if segments.len() < 2 || !segments[..2].iter().all(|s| is_valid_name(s)) {
+        return None;
+    }
+    if segments.iter().any(|s| matches!(*s, "" | "." | "..")) {
+        return None;
+    }
+    let encoded: Vec<_> = segments
+        .iter()
+        .map(|segment| {
+            segment
+                .bytes()
+                .map(|b| match b {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                        char::from(b).to_string()
+                    }
+                    _ => format!("%{b:02X}"),
+                })
+                .collect::<String>()
+        })
+        .collect();
+    Some(encoded.join("/"))
+}
+
+/// Imports a project from a zip archive through the backend.
+pub async fn import_zip(zip: &[u8]) -> Result<ImportedProject<'static>, RequestError> {
+    let req = post(api::IMPORT_PATH)
+        .header("Content-Type", "application/zip")
+        .body(Uint8Array::from(zip));
+    let res = send(req).await?;
+    Ok(res.json::<ImportedProject>().await?)
+}
+
+/// Opens a project that was imported from the file `name`. The editor cannot be reset once it is
+/// mounted, so the project is kept in session storage and the playground is reloaded without the
+/// query parameters, which would load other code.
+pub fn open_imported_project(name: &str, project: &ImportedProject) -> Result<(), String> {
+    SessionStorage::set(IMPORTED_PROJECT_KEY, (name, project)).map_err(|err| err.to_string())?;
+    let location = web_sys::window().unwrap().location();
+    let path = location.pathname().map_err(|err| format!("{err:?}"))?;
+    location.set_href(&path).map_err(|err| format!("{err:?}"))
+}
+
+/// Takes the project that was opened with [`open_imported_project`] before the reload, with the
+/// name of its file.
+pub fn take_imported_project() -> Option<(String, ImportedProject<'static>)> {
+    let project = SessionStorage::get(IMPORTED_PROJECT_KEY).ok();
+    SessionStorage::delete(IMPORTED_PROJECT_KEY);
+    project
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repo_segments_are_encoded() {
+        assert_eq!(
+            encode_repo("sycamore-rs/sycamore/examples/hello world").as_deref(),
+            Some("sycamore-rs/sycamore/examples/hello%20world")
+        );
+        assert_eq!(encode_repo("/owner/name/").as_deref(), Some("owner/name"));
+        assert_eq!(
+            encode_repo("owner/name/a?b#c%").as_deref(),
+            Some("owner/name/a%3Fb%23c%25")
+        );
+    }
+
+    #[test]
+    fn invalid_repos_are_rejected() {
+        for repo in [
+            "owner",
+            "owner/",
+            "own?er/name",
+            "owner/na#me",
+            "owner/..",
+            "owner/name/../../paste",
+            "owner/name/./dir",
+            "owner/name//dir",
+        ] {
+            assert_eq!(encode_repo(repo), None, "{repo}");
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! The modal that is shown after sharing a code snippet.
+
+use js_sys::Promise;
+use qrcode::render::svg;
+use qrcode::{EcLevel, QrCode};
+use sycamore::futures::spawn_local_scoped;
+use sycamore::prelude::*;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::toast::use_toasts;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = ["navigator", "clipboard"], js_name = "writeText", catch)]
+    fn write_text(text: &str) -> Result<Promise, JsValue>;
+}
+
+/// Copies the text to the user's clipboard.
+async fn copy_to_clipboard(text: &str) -> Result<(), JsValue> {
+    JsFuture::from(write_text(text)?).await?;
+    Ok(())
+}
+
+/// Renders the text as a QR code in SVG format. Returns `None` if the text is too long to fit in
+/// a QR code.
+fn qr_code_svg(text: &str) -> Option<String> {
+    let code = QrCode::with_error_correction_level(text, EcLevel::L).ok()?;
+    Some(
+        code.render::<svg::Color>()
+            .min_dimensions(256, 256)
+            .quiet_zone(true)
+            .build(),
+    )
+}
+
+#[derive(Prop)]
+struct CopyButtonProps<'a> {
+    text: &'a ReadSignal<String>,
+}
+
+#[component]
+fn CopyButton<'a, G: Html>(cx: Scope<'a>, props: CopyButtonProps<'a>) -> View<G> {
+    let toasts = use_toasts(cx);
+    let copy = move |_| {
+        spawn_local_scoped(cx, async move {
+            match copy_to_clipboard(&props.text.get()).await {
+                Ok(()) => toasts.info("Copied to clipboard."),
+                Err(err) => {
+                    log::error!("Could not copy to clipboard: {err:?}");
+                    toasts.error("Could not copy to clipboard.");
+                }
+            }
+        });
+    };
+
+    view! { cx,
+        button(
+            type="button",
+            class="px-2 ml-2 text-sm bg-gray-200 hover:bg-gray-300 rounded",
+            on:click=copy,
+        ) { "Copy" }
+    }
+}
+
+#[derive(Prop)]
+pub struct ShareModalProps<'a> {
+    open: &'a Signal<bool>,
+    /// The URL of the gist that was created, if the snippet was shared as a gist.
+    gist_url: &'a ReadSignal<Option<String>>,
+    playground_url: &'a ReadSignal<String>,
+}
+
+#[component]
+pub fn ShareModal<'a, G: Html>(cx: Scope<'a>, props: ShareModalProps<'a>) -> View<G> {
+    let playground_url = props.playground_url;
+    let markdown = playground_url.map(cx, |url| {
+        format!("[Open in the Sycamore Playground]({url})")
+    });
+    let show_qr_code = create_signal(cx, false);
+    let qr_code = create_memo(cx, || {
+        if *show_qr_code.get() {
+            qr_code_svg(&playground_url.get())
+        } else {
+            None
+        }
+    });
+    let close = |_| {
+        props.open.set(false);
+        show_qr_code.set(false);
+    };
+
+    view! { cx,
+        // Background dim.
+        div(class=format!("fixed inset-0 w-full h-full z-40 bg-gray-500 bg-opacity-75 transition-opacity {}", if *props.open.get() { "" } else { "hidden" }))
+        // Share modal.
+        div(
+            class=format!("fixed inset-0 w-full z-50 {}", if *props.open.get() { "" } else { "hidden" }),
+            role="dialog",
+            aria-modal=true,
+        ) {
+            // Modal content.
+            div(class="bg-white container mx-auto mt-5 px-5 py-3 rounded shadow-lg") {
+                h1(class="text-xl font-bold") { "Share" }
+                (match props.gist_url.get().as_ref().clone() {
+                    Some(url) => {
+                        let text = url.clone();
+                        let gist_url = create_signal(cx, url.clone());
+                        view! { cx,
+                            p { "GitHub Gist: "
+                                a(class="text-blue-600 underline", href=url) { (text) }
+                                CopyButton(text=gist_url)
+                            }
+                        }
+                    }
+                    None => view! { cx, },
+                })
+                p(class="break-all") { "Runnable playground: "
+                    a(class="text-blue-600 underline", href=playground_url.get()) { (playground_url.get()) }
+                    CopyButton(text=playground_url)
+                }
+                p { "Markdown link: "
+                    CopyButton(text=markdown)
+                }
+                p {
+                    button(
+                        type="button",
+                        class="text-blue-600 underline",
+                        on:click=|_| show_qr_code.set(!*show_qr_code.get()),
+                    ) { (if *show_qr_code.get() { "Hide QR code" } else { "Show QR code" }) }
+                }
+                (if *show_qr_code.get() {
+                    match qr_code.get().as_ref().clone() {
+                        Some(svg) => view! { cx,
+                            div(class="w-64 h-64", dangerously_set_inner_html=&svg)
+                        },
+                        None => view! { cx,
+                            p { "This link is too long to fit in a QR code." }
+                        },
+                    }
+                } else {
+                    view! { cx, }
+                })
+                button(type="button", class="px-5 bg-yellow-400 font-bold text-white rounded shadow-inner", on:click=close) { "Done" }
+            }
+        }
+    }
+}
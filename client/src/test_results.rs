@@ -0,0 +1,92 @@
+use playground_common::api::{
+    TestKind, TestOutcome, TestRequest, TestResponse, TestResult, TEST_PATH,
+};
+use sycamore::prelude::*;
+use web_sys::AbortSignal;
+
+use crate::api::{post, send, RequestError};
+
+/// Builds and runs the tests in the code on the server. The request is aborted when `signal` is.
+pub async fn run_tests(code: &str, signal: &AbortSignal) -> Result<TestResponse, RequestError> {
+    let req = post(TEST_PATH)
+        .abort_signal(Some(signal))
+        .json(&TestRequest { code: code.into() })?;
+    Ok(send(req).await?.json().await?)
+}
+
+#[derive(Prop)]
+pub struct TestResultListProps {
+    results: Vec<TestResult>,
+    output: String,
+}
+
+/// Shows the outcome of every test, with the output of the tests that failed, and the full output
+/// of the test harnesses.
+#[component]
+pub fn TestResultList<G: Html>(cx: Scope, props: TestResultListProps) -> View<G> {
+    let count = |outcome| {
+        props
+            .results
+            .iter()
+            .filter(|result| result.outcome == outcome)
+            .count()
+    };
+    let summary = if props.results.is_empty() {
+        "No tests found.".to_string()
+    } else {
+        format!(
+            "{} passed, {} failed, {} ignored.",
+            count(TestOutcome::Passed),
+            count(TestOutcome::Failed),
+            count(TestOutcome::Ignored)
+        )
+    };
+    let list = View::new_fragment(
+        props
+            .results
+            .into_iter()
+            .map(|result| {
+                let (status, class) = match result.outcome {
+                    TestOutcome::Passed => ("ok", "w-16 font-bold text-green-600"),
+                    TestOutcome::Failed => ("FAILED", "w-16 font-bold text-red-600"),
+                    TestOutcome::Ignored => ("ignored", "w-16 font-bold text-gray-500"),
+                };
+                let kind = match result.kind {
+                    TestKind::Native => "native",
+                    TestKind::Wasm => "wasm",
+                };
+                let name = result.name;
+                let output = result.output;
+                view! { cx,
+                    li(class="py-1") {
+                        div(class="flex flex-row") {
+                            span(class=class) { (status) }
+                            span(class="grow font-mono") { (name) }
+                            span(class="px-2 text-gray-500") { (kind) }
+                        }
+                        (if output.is_empty() {
+                            view! { cx, }
+                        } else {
+                            let output = output.clone();
+                            view! { cx,
+                                pre(class="ml-16 text-sm overflow-x-auto") { (output) }
+                            }
+                        })
+                    }
+                }
+            })
+            .collect(),
+    );
+    let output = props.output;
+
+    view! { cx,
+        div {
+            p { (summary) }
+            ul(class="divide-y divide-gray-200") { (list) }
+            details(class="pt-2") {
+                summary(class="cursor-pointer") { "Full output" }
+                pre(class="text-sm") { (output) }
+            }
+        }
+    }
+}
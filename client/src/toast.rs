@@ -0,0 +1,112 @@
+//! Toast notifications for reporting errors and other feedback to the user.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use gloo_timers::future::TimeoutFuture;
+use sycamore::futures::spawn_local_scoped;
+use sycamore::prelude::*;
+
+/// How long a toast is shown before it is dismissed automatically, in milliseconds.
+const TOAST_DURATION_MS: u32 = 6000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Info,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toast {
+    id: u32,
+    kind: ToastKind,
+    message: String,
+}
+
+/// The list of toasts that are currently shown. This is provided as a context at the root of the
+/// app and can be accessed with [`use_toasts`].
+#[derive(Default)]
+pub struct Toasts {
+    toasts: RcSignal<Vec<Toast>>,
+    next_id: Rc<Cell<u32>>,
+}
+
+impl Toasts {
+    /// Shows a new toast.
+    pub fn show(&self, kind: ToastKind, message: impl Into<String>) {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.toasts.modify().push(Toast {
+            id,
+            kind,
+            message: message.into(),
+        });
+    }
+
+    /// Shows a new informational toast.
+    pub fn info(&self, message: impl Into<String>) {
+        self.show(ToastKind::Info, message);
+    }
+
+    /// Shows a new error toast.
+    pub fn error(&self, message: impl Into<String>) {
+        self.show(ToastKind::Error, message);
+    }
+
+    /// Removes the toast with the given id.
+    pub fn dismiss(&self, id: u32) {
+        self.toasts.modify().retain(|toast| toast.id != id);
+    }
+}
+
+/// Returns the [`Toasts`] provided at the root of the app.
+pub fn use_toasts(cx: Scope<'_>) -> &Toasts {
+    use_context(cx)
+}
+
+#[component]
+fn ToastView<G: Html>(cx: Scope, toast: Toast) -> View<G> {
+    let toasts = use_toasts(cx);
+    let id = toast.id;
+    // Dismiss the toast automatically after a while. The future is cancelled if the toast is
+    // dismissed manually before then.
+    spawn_local_scoped(cx, async move {
+        TimeoutFuture::new(TOAST_DURATION_MS).await;
+        toasts.dismiss(id);
+    });
+
+    let color = match toast.kind {
+        ToastKind::Info => "bg-gray-700",
+        ToastKind::Error => "bg-red-600",
+    };
+    view! { cx,
+        div(
+            class=format!("flex flex-row items-start px-4 py-2 text-white rounded shadow-lg {color}"),
+            role="alert",
+        ) {
+            p(class="grow whitespace-pre-wrap") { (toast.message.clone()) }
+            button(
+                type="button",
+                class="ml-4 font-bold",
+                aria-label="Dismiss",
+                on:click=move |_| toasts.dismiss(id),
+            ) { "×" }
+        }
+    }
+}
+
+/// Renders the toasts that are currently shown in the bottom right corner of the screen.
+#[component]
+pub fn ToastContainer<G: Html>(cx: Scope) -> View<G> {
+    let toasts = use_toasts(cx);
+
+    view! { cx,
+        div(class="fixed bottom-0 right-0 z-50 p-4 w-full max-w-md flex flex-col space-y-2") {
+            Keyed(
+                iterable=&toasts.toasts,
+                view=|cx, toast| view! { cx, ToastView(toast) },
+                key=|toast| toast.id,
+            )
+        }
+    }
+}
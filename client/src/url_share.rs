@@ -0,0 +1,60 @@
+//! Sharing code snippets directly in the URL, without a round-trip through the backend.
+
+use std::error::Error;
+use std::io::{Read, Write};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+/// The maximum length of the encoded code that is put into a share URL. Larger snippets are
+/// shared as a gist instead.
+pub const MAX_ENCODED_LEN: usize = 2000;
+
+/// The maximum length of decoded code, the default limit of the server. Deflate compresses
+/// repetitive input by orders of magnitude, so a short crafted link could otherwise expand to
+/// gigabytes.
+pub const MAX_CODE_LEN: u64 = 100 * 1024;
+
+/// Compresses the code with deflate and encodes the result as URL-safe base64 so that it can be
+/// used directly in the `?code=` query parameter.
+pub fn encode_code(code: &str) -> String {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder
+        .write_all(code.as_bytes())
+        .expect("writing to a Vec cannot fail");
+    let compressed = encoder.finish().expect("writing to a Vec cannot fail");
+    base64::encode_config(compressed, base64::URL_SAFE_NO_PAD)
+}
+
+/// Decodes code that was encoded with [`encode_code`].
+pub fn decode_code(encoded: &str) -> Result<String, Box<dyn Error>> {
+    let compressed = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)?;
+    let mut code = Vec::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .take(MAX_CODE_LEN + 1)
+        .read_to_end(&mut code)?;
+    if code.len() as u64 > MAX_CODE_LEN {
+        return Err(format!("the code is larger than {MAX_CODE_LEN} bytes").into());
+    }
+    Ok(String::from_utf8(code)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let code = "fn main() {\n    println!(\"Hello, world!\");\n}\n";
+        assert_eq!(decode_code(&encode_code(code)).unwrap(), code);
+    }
+
+    #[test]
+    fn rejects_code_over_the_limit() {
+        let code = "a".repeat(MAX_CODE_LEN as usize + 1);
+        assert!(decode_code(&encode_code(&code)).is_err());
+        let code = "a".repeat(MAX_CODE_LEN as usize);
+        assert_eq!(decode_code(&encode_code(&code)).unwrap(), code);
+    }
+}
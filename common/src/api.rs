@@ -0,0 +1,389 @@
+//! The HTTP API between the playground client and the compiler service.
+//!
+//! Every request sent by the client carries the [`VERSION`] of the API it was built against in
+//! the [`VERSION_HEADER`] header. The server rejects requests made against a different version
+//! with [`ApiError::VersionMismatch`] and echoes its own version in every response so that the
+//! client can detect the mismatch as well.
+//!
+//! Request bodies and error responses are JSON. The body of a successful `/compile` or job artifact
+//! response is serialized with the [`Encoding`](crate::encoding::Encoding) negotiated through the
+//! `Accept` header, which is bincode by default. Assets and bundles are served as files, and all
+//! other successful responses are JSON.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bundle::PageHead;
+
+/// The version of the API. This should be incremented whenever a breaking change is made to any
+/// of the types or endpoints in this module.
+pub const VERSION: u32 = 8;
+
+/// The name of the header that carries the API version in both requests and responses.
+pub const VERSION_HEADER: &str = "x-playground-api-version";
+
+/// The name of the header that carries the id of a request. The server generates an id for every
+/// request that does not have one and echoes it in the response, so that failures reported by
+/// users can be found in the server logs.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The name of the header that carries the API key of clients that have one. Requests with a
+/// known key are rate limited per key instead of per IP address.
+pub const API_KEY_HEADER: &str = "x-playground-api-key";
+
+/// `POST`: compile code. Takes a [`CompileRequest`] and returns a [`CompileResponse`].
+///
+/// The compile runs as a job on the server. If it does not finish before the request times out,
+/// the server responds with `202 Accepted` and a [`CompileJob`] instead. The result can then be
+/// fetched from [`job_artifact_path`] once [`job_path`] reports that the job is finished.
+pub const COMPILE_PATH: &str = "/compile";
+
+/// `POST`: start a compile job without waiting for it. Takes a [`CompileRequest`] and returns a
+/// [`CompileJob`] with status `202 Accepted`.
+pub const JOBS_PATH: &str = "/jobs";
+
+/// `POST`: run the tests in the code. Takes a [`TestRequest`] and returns a [`TestResponse`].
+///
+/// `#[test]` functions run natively, and `#[wasm_bindgen_test]` functions run in Node.js. Tests
+/// that are configured to run in a browser are not supported.
+pub const TEST_PATH: &str = "/test";
+
+/// `GET`: the status of a compile job. Returns a [`JobStatus`].
+///
+/// `DELETE`: cancel the job, killing the build if it is running. Returns `204 No Content`.
+pub fn job_path(id: &str) -> String {
+    format!("{JOBS_PATH}/{id}")
+}
+
+/// `GET`: the result of a finished compile job. Returns a [`CompileResponse`]. A successful result
+/// can only be fetched once, the job is removed afterwards.
+pub fn job_artifact_path(id: &str) -> String {
+    format!("{JOBS_PATH}/{id}/artifact")
+}
+
+/// `GET`: an asset of a successful build, see [`CompileRequest::assets`]. Returns the contents
+/// of the file.
+pub fn asset_path(code_hash: &str, path: &str) -> String {
+    format!("/assets/{code_hash}/{path}")
+}
+
+/// `GET`: a successful build as a zip archive that can be hosted statically, see
+/// [`Bundle`](crate::bundle::Bundle). The archive uses the head that the build was compiled with,
+/// see [`CompileRequest::head`]. Returns
+/// `404 Not Found` once the build has been evicted from the cache.
+pub fn bundle_path(code_hash: &str) -> String {
+    format!("/bundles/{code_hash}")
+}
+
+/// `GET`: import a project that builds with trunk from a public GitHub repository. `repo` is
+/// `owner/name`, optionally followed by the path of the project in the repository. Returns an
+/// [`ImportedProject`].
+pub fn repo_path(repo: &str) -> String {
+    format!("/repos/{repo}")
+}
+
+/// `POST`: import a project that builds with trunk from a zip archive, sent as the body with
+/// `Content-Type: application/zip`. The project is in the directory of the archive with the
+/// `Cargo.toml` closest to the root. Returns an [`ImportedProject`].
+pub const IMPORT_PATH: &str = "/import";
+
+/// `POST`: create a new paste. Takes a [`CreatePasteRequest`] and returns a
+/// [`CreatePasteResponse`].
+pub const PASTE_PATH: &str = "/paste";
+
+/// `GET`: fetch an existing paste. Returns a [`GetPasteResponse`].
+pub fn get_paste_path(id: &str) -> String {
+    format!("{PASTE_PATH}/{id}")
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompileRequest<'a> {
+    pub code: Cow<'a, str>,
+    /// Static files that the app can fetch from relative URLs. They are served from
+    /// [`asset_path`] after a successful build.
+    #[serde(default)]
+    pub assets: Vec<Asset<'a>>,
+    /// The head of the page that the app runs in. It is part of the build so that bundles of the
+    /// build, see [`bundle_path`], contain the page that the app was written for.
+    #[serde(default)]
+    pub head: PageHead,
+}
+
+/// A static file, e.g. an image or a JSON fixture.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Asset<'a> {
+    /// The path of the file relative to the app, e.g. `images/logo.png`.
+    pub path: Cow<'a, str>,
+    /// The contents of the file. This is encoded as a base64 string in JSON.
+    #[serde(with = "crate::encoding::bytes")]
+    pub data: Cow<'a, [u8]>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum CompileResponse<'a> {
+    Success {
+        js: Cow<'a, str>,
+        /// How `js` has to be loaded.
+        target: JsTarget,
+        /// The wasm binary. This is encoded as a base64 string in JSON.
+        #[serde(with = "crate::encoding::bytes")]
+        wasm: Cow<'a, [u8]>,
+        /// Identifies the build by its code, assets and head. The assets are served from
+        /// [`asset_path`] with this hash.
+        code_hash: String,
+    },
+    /// cargo could not compile the code. Contains the output of cargo.
+    CompileError(String),
+    /// The code compiled, but wasm-bindgen could not generate JS glue code for it, e.g. because
+    /// the versions of `wasm-bindgen` do not match. Contains the error reported by wasm-bindgen.
+    BundleError(String),
+}
+
+/// The kind of JS glue code that wasm-bindgen generates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JsTarget {
+    /// An ES module whose default export initializes the wasm module.
+    Web,
+    /// A classic script that defines a global `wasm_bindgen` function which initializes the wasm
+    /// module.
+    NoModules,
+}
+
+impl FromStr for JsTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "web" => Ok(Self::Web),
+            "no-modules" => Ok(Self::NoModules),
+            _ => Err(format!(
+                "unknown JS target {s:?}, expected `web` or `no-modules`"
+            )),
+        }
+    }
+}
+
+impl CompileResponse<'_> {
+    /// Clones any borrowed data so that the response can outlive the buffer it was decoded from.
+    pub fn into_owned(self) -> CompileResponse<'static> {
+        match self {
+            Self::Success {
+                js,
+                target,
+                wasm,
+                code_hash,
+            } => CompileResponse::Success {
+                js: Cow::Owned(js.into_owned()),
+                target,
+                wasm: Cow::Owned(wasm.into_owned()),
+                code_hash,
+            },
+            Self::CompileError(err) => CompileResponse::CompileError(err),
+            Self::BundleError(err) => CompileResponse::BundleError(err),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TestRequest<'a> {
+    pub code: Cow<'a, str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TestResponse {
+    /// The tests ran. `output` is the output of the test harnesses, for showing what happened if
+    /// the results are not enough, e.g. when a test crashed the harness.
+    Finished {
+        results: Vec<TestResult>,
+        output: String,
+    },
+    /// cargo could not compile the code or the tests. Contains the output of cargo.
+    CompileError(String),
+    /// The tests compiled, but could not be run. Contains the reason.
+    RunError(String),
+}
+
+/// The result of a single test.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestResult {
+    /// The path of the test function, e.g. `tests::it_works`.
+    pub name: String,
+    pub kind: TestKind,
+    pub outcome: TestOutcome,
+    /// What the test printed and why it failed. Empty for tests that passed.
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestKind {
+    /// A `#[test]` function, run natively.
+    Native,
+    /// A `#[wasm_bindgen_test]` function, run in Node.js.
+    Wasm,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestOutcome {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+/// Returned by [`JOBS_PATH`], and by [`COMPILE_PATH`] when the compile takes longer than the
+/// request timeout.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompileJob {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum JobStatus {
+    /// The job is waiting for other builds to finish.
+    Queued,
+    /// The code is being compiled by cargo.
+    Building,
+    /// The JS glue code for the wasm binary is being generated.
+    Bundling,
+    /// The result can be fetched from [`job_artifact_path`].
+    Done,
+    /// The job failed because of an error on the server. Errors in the code are reported as
+    /// [`CompileResponse::CompileError`] or [`CompileResponse::BundleError`] instead.
+    Failed { message: String },
+    /// The build took longer than the server allows and was killed.
+    TimedOut,
+    /// The job was cancelled.
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, Self::Queued | Self::Building | Self::Bundling)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePasteRequest<'a> {
+    pub code: Cow<'a, str>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePasteResponse {
+    /// The id of the gist that was created.
+    pub id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GetPasteResponse<'a> {
+    pub code: Cow<'a, str>,
+}
+
+/// A project imported from a repository, mapped onto the files of the playground.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportedProject<'a> {
+    /// `src/main.rs`, with the modules in other files of `src` inlined.
+    pub code: Cow<'a, str>,
+    /// The `<head>` of `index.html` without the tags that trunk processes, and the styles that
+    /// they link to.
+    pub head: PageHead,
+    /// The files that trunk copies into the output directory.
+    pub assets: Vec<Asset<'a>>,
+    /// The dependencies of the project that are not available in the playground.
+    pub unsupported_dependencies: Vec<String>,
+    /// Parts of the project that could not be imported, for showing to the user.
+    pub warnings: Vec<String>,
+}
+
+/// An error returned by any of the endpoints. This is serialized as JSON in the response body
+/// along with the matching [status code](ApiError::status_code).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub enum ApiError {
+    /// The client and the server were built against different versions of the API.
+    VersionMismatch { client: u32, server: u32 },
+    /// The request was malformed.
+    BadRequest { message: String },
+    /// The request body or the code in it is larger than the server allows. `limit` is the
+    /// maximum size in bytes.
+    PayloadTooLarge { limit: u64 },
+    /// The request was well-formed but its content was rejected, e.g. because the code contains
+    /// a null byte.
+    InvalidInput { message: String },
+    /// None of the encodings in the `Accept` header are supported.
+    NotAcceptable,
+    /// Too many requests were made, either to the server or by the server to GitHub. Responses
+    /// to requests that were limited by the server carry a `Retry-After` header.
+    RateLimited,
+    /// The requested resource does not exist.
+    NotFound,
+    /// The request or the build took too long to process.
+    Timeout,
+    /// The artifact of a compile job was requested before the job finished.
+    JobNotFinished,
+    /// The compile job was cancelled before it finished.
+    JobCancelled,
+    /// GitHub could not be reached or returned an unexpected response.
+    Upstream { message: String },
+    /// The server is temporarily or permanently unable to handle the request.
+    Unavailable { message: String },
+    /// Any other error on the server.
+    Internal { message: String },
+}
+
+impl ApiError {
+    /// The HTTP status code of responses that carry this error.
+    pub fn status_code(&self) -> u16 {
+        match self {
+            Self::VersionMismatch { .. } | Self::BadRequest { .. } => 400,
+            Self::NotFound => 404,
+            Self::NotAcceptable => 406,
+            Self::Timeout => 408,
+            Self::JobNotFinished => 409,
+            Self::JobCancelled => 410,
+            Self::PayloadTooLarge { .. } => 413,
+            Self::InvalidInput { .. } => 422,
+            Self::RateLimited => 429,
+            Self::Internal { .. } => 500,
+            Self::Upstream { .. } => 502,
+            Self::Unavailable { .. } => 503,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::VersionMismatch { client, server } => write!(
+                f,
+                "The playground was updated (client API version {client}, server API version \
+                 {server}). Please reload the page."
+            ),
+            Self::BadRequest { message } => write!(f, "Bad request: {message}"),
+            Self::PayloadTooLarge { limit } => {
+                write!(f, "The request is too large. The limit is {limit} bytes.")
+            }
+            Self::InvalidInput { message } => write!(f, "Invalid input: {message}"),
+            Self::RateLimited => write!(f, "Too many requests. Please try again later."),
+            Self::NotFound => write!(f, "Not found."),
+            Self::NotAcceptable => write!(
+                f,
+                "None of the accepted content types are supported. Supported are \
+                 application/x-bincode, application/json and application/msgpack."
+            ),
+            Self::Timeout => write!(f, "The request timed out."),
+            Self::JobNotFinished => write!(f, "The compile job has not finished yet."),
+            Self::JobCancelled => write!(f, "The compile job was cancelled."),
+            Self::Upstream { message } => write!(f, "GitHub is unavailable: {message}"),
+            Self::Unavailable { message } => write!(f, "Service unavailable: {message}"),
+            Self::Internal { message } => write!(f, "Internal server error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
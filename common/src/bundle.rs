@@ -0,0 +1,139 @@
+//! Standalone bundles of compiled apps.
+//!
+//! A bundle contains everything that is needed to host an app statically: an `index.html` that
+//! loads the JS glue code, the glue code, the wasm binary and the assets of the build.
+
+use std::io::{self, Cursor, Write};
+
+use serde::{Deserialize, Serialize};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::api::{Asset, JsTarget};
+
+/// The Tailwind Play CDN. Generates the styles for the classes used in the document at runtime.
+/// It is meant for development, not production, but it is also what bundles and exported projects
+/// use, since the playground does not run Tailwind at build time.
+pub static TAILWIND_CDN_URL: &str = "https://cdn.tailwindcss.com";
+
+/// The file name of the JS glue code in a bundle.
+pub static JS_FILE_NAME: &str = "playground.js";
+/// The file name of the wasm binary in a bundle.
+pub static WASM_FILE_NAME: &str = "playground_bg.wasm";
+/// The file name of the document in a bundle.
+pub static INDEX_FILE_NAME: &str = "index.html";
+
+/// The parts of the document that the user can edit besides the code.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PageHead {
+    /// Extra HTML that is added to the `<head>` of the document.
+    pub html: String,
+    /// The contents of `style.css`.
+    pub css: String,
+    /// Process `css` with Tailwind and generate styles for the Tailwind classes used by the app.
+    pub tailwind: bool,
+}
+
+/// Builds a document with the user's head. Relative URLs are resolved against `base_url` if it is
+/// set. `scripts` is added at the end of the `<head>` and loads the app.
+pub fn document(head: &PageHead, base_url: Option<&str>, scripts: &str) -> String {
+    let PageHead {
+        html,
+        css,
+        tailwind,
+    } = head;
+    let base = base_url
+        .map(|url| format!(r#"<base href="{url}" />"#))
+        .unwrap_or_default();
+    let (tailwind_script, style_type) = if *tailwind {
+        (
+            format!(r#"<script src="{TAILWIND_CDN_URL}"></script>"#),
+            r#" type="text/tailwindcss""#,
+        )
+    } else {
+        (String::new(), "")
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+    <head>
+        <meta content="text/html;charset=utf-8" http-equiv="Content-Type" />
+        {base}
+        {tailwind_script}
+        {html}
+        <style{style_type}>
+{css}
+        </style>
+        {scripts}
+    </head>
+    <body>
+        <noscript>You need to enable Javascript to run this interactive app.</noscript>
+    </body>
+</html>
+"#
+    )
+}
+
+/// The `index.html` of a bundle. It loads the glue code and the wasm binary from next to it and
+/// starts the app.
+pub fn index_html(head: &PageHead, target: JsTarget) -> String {
+    let scripts = match target {
+        JsTarget::Web => format!(
+            r#"<script type="module">
+            import init from "./{JS_FILE_NAME}";
+            init("./{WASM_FILE_NAME}");
+        </script>"#
+        ),
+        JsTarget::NoModules => format!(
+            r#"<script src="./{JS_FILE_NAME}"></script>
+        <script>
+            wasm_bindgen("./{WASM_FILE_NAME}");
+        </script>"#
+        ),
+    };
+    document(head, None, &scripts)
+}
+
+/// A compiled app with everything that is needed to host it.
+#[derive(Debug, Clone)]
+pub struct Bundle<'a> {
+    pub js: &'a str,
+    pub target: JsTarget,
+    pub wasm: &'a [u8],
+    pub assets: &'a [Asset<'a>],
+    pub head: &'a PageHead,
+}
+
+impl Bundle<'_> {
+    /// Packages the bundle into a zip archive. The generated files are at the root of the archive,
+    /// and the assets are next to them.
+    pub fn to_zip(&self) -> io::Result<Vec<u8>> {
+        let index_html = index_html(self.head, self.target);
+        let generated = [
+            (INDEX_FILE_NAME, index_html.as_bytes()),
+            (JS_FILE_NAME, self.js.as_bytes()),
+            (WASM_FILE_NAME, self.wasm),
+        ];
+        // An asset with the name of a generated file would replace it and break the bundle.
+        let assets = self
+            .assets
+            .iter()
+            .filter(|asset| generated.iter().all(|(name, _)| *name != asset.path))
+            .map(|asset| (asset.path.as_ref(), asset.data.as_ref()));
+        write_zip(generated.into_iter().chain(assets))
+    }
+}
+
+/// Writes files into a zip archive. The files are given as their paths in the archive and their
+/// contents.
+pub(crate) fn write_zip<'a>(
+    files: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+) -> io::Result<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (path, data) in files {
+        zip.start_file(path, options)?;
+        zip.write_all(data)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
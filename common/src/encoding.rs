@@ -0,0 +1,255 @@
+//! Encodings that responses can be serialized with.
+//!
+//! The encoding of a response is negotiated using the `Accept` header of the request. The web
+//! client uses bincode because it is compact and fast to decode. JSON and MessagePack are offered
+//! for tools that are not written in Rust.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Bincode,
+    Json,
+    MessagePack,
+}
+
+impl Encoding {
+    /// The encoding that is used if the client does not ask for a specific one.
+    pub const DEFAULT: Self = Self::Bincode;
+
+    /// The MIME type that is sent in the `Content-Type` header of responses with this encoding.
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Bincode => "application/x-bincode",
+            Self::Json => "application/json",
+            Self::MessagePack => "application/msgpack",
+        }
+    }
+
+    fn from_mime_type(mime_type: &str) -> Option<Self> {
+        match mime_type {
+            "*/*" | "application/*" | "application/octet-stream" | "application/x-bincode" => {
+                Some(Self::Bincode)
+            }
+            "application/json" => Some(Self::Json),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(Self::MessagePack)
+            }
+            _ => None,
+        }
+    }
+
+    /// Picks the encoding with the highest quality value in an `Accept` header. Returns
+    /// [`Encoding::DEFAULT`] if there is no header and `None` if none of the accepted MIME types
+    /// are supported.
+    pub fn from_accept(accept: Option<&str>) -> Option<Self> {
+        let accept = match accept {
+            Some(accept) if !accept.trim().is_empty() => accept,
+            _ => return Some(Self::DEFAULT),
+        };
+        let mut best: Option<(Self, f32)> = None;
+        for media_range in accept.split(',') {
+            let mut params = media_range.split(';').map(str::trim);
+            let mime_type = params.next().unwrap_or_default().to_ascii_lowercase();
+            let quality = params
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if let Some(encoding) = Self::from_mime_type(&mime_type) {
+                let is_better = match best {
+                    Some((_, best_quality)) => quality > best_quality,
+                    None => quality > 0.0,
+                };
+                if is_better {
+                    best = Some((encoding, quality));
+                }
+            }
+        }
+        best.map(|(encoding, _)| encoding)
+    }
+
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, EncodingError> {
+        match self {
+            Self::Bincode => bincode::serialize(value).map_err(EncodingError::Bincode),
+            Self::Json => serde_json::to_vec(value).map_err(EncodingError::Json),
+            Self::MessagePack => {
+                rmp_serde::to_vec_named(value).map_err(EncodingError::MessagePackEncode)
+            }
+        }
+    }
+
+    pub fn decode<'de, T: Deserialize<'de>>(self, bytes: &'de [u8]) -> Result<T, EncodingError> {
+        match self {
+            Self::Bincode => bincode::deserialize(bytes).map_err(EncodingError::Bincode),
+            Self::Json => serde_json::from_slice(bytes).map_err(EncodingError::Json),
+            Self::MessagePack => {
+                rmp_serde::from_slice(bytes).map_err(EncodingError::MessagePackDecode)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum EncodingError {
+    Bincode(bincode::Error),
+    Json(serde_json::Error),
+    MessagePackEncode(rmp_serde::encode::Error),
+    MessagePackDecode(rmp_serde::decode::Error),
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bincode(err) => write!(f, "bincode: {err}"),
+            Self::Json(err) => write!(f, "JSON: {err}"),
+            Self::MessagePackEncode(err) => write!(f, "MessagePack: {err}"),
+            Self::MessagePackDecode(err) => write!(f, "MessagePack: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
+/// Serializes binary data as a base64 string in human readable formats such as JSON and as raw
+/// bytes in binary formats. Use with `#[serde(with = "crate::encoding::bytes")]`.
+pub(crate) mod bytes {
+    use std::borrow::Cow;
+
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&base64::encode(bytes))
+        } else {
+            serializer.serialize_bytes(bytes)
+        }
+    }
+
+    pub fn deserialize<'de, 'a, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Cow<'a, [u8]>, D::Error> {
+        if deserializer.is_human_readable() {
+            let encoded = String::deserialize(deserializer)?;
+            base64::decode(encoded.as_bytes())
+                .map(Cow::Owned)
+                .map_err(D::Error::custom)
+        } else {
+            let bytes = serde_bytes::ByteBuf::deserialize(deserializer)?;
+            Ok(Cow::Owned(bytes.into_vec()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+
+    #[test]
+    fn missing_or_empty_accept_uses_default() {
+        assert_eq!(Encoding::from_accept(None), Some(Encoding::DEFAULT));
+        assert_eq!(Encoding::from_accept(Some("")), Some(Encoding::DEFAULT));
+        assert_eq!(Encoding::from_accept(Some("  ")), Some(Encoding::DEFAULT));
+    }
+
+    #[test]
+    fn wildcards_map_to_bincode() {
+        assert_eq!(Encoding::from_accept(Some("*/*")), Some(Encoding::Bincode));
+        assert_eq!(
+            Encoding::from_accept(Some("application/*")),
+            Some(Encoding::Bincode)
+        );
+    }
+
+    #[test]
+    fn exact_types() {
+        assert_eq!(
+            Encoding::from_accept(Some("application/json")),
+            Some(Encoding::Json)
+        );
+        assert_eq!(
+            Encoding::from_accept(Some("Application/JSON")),
+            Some(Encoding::Json)
+        );
+        assert_eq!(
+            Encoding::from_accept(Some("application/x-msgpack")),
+            Some(Encoding::MessagePack)
+        );
+        assert_eq!(
+            Encoding::from_accept(Some("application/x-bincode")),
+            Some(Encoding::Bincode)
+        );
+    }
+
+    #[test]
+    fn highest_quality_wins() {
+        assert_eq!(
+            Encoding::from_accept(Some("application/json;q=0.5, application/msgpack")),
+            Some(Encoding::MessagePack)
+        );
+        assert_eq!(
+            Encoding::from_accept(Some("application/json, */*;q=0.1")),
+            Some(Encoding::Json)
+        );
+        assert_eq!(
+            Encoding::from_accept(Some("*/*; q=0.1, application/msgpack; q=0.8")),
+            Some(Encoding::MessagePack)
+        );
+    }
+
+    #[test]
+    fn equal_quality_keeps_the_first_type() {
+        assert_eq!(
+            Encoding::from_accept(Some("application/msgpack, application/json")),
+            Some(Encoding::MessagePack)
+        );
+    }
+
+    #[test]
+    fn unknown_types_are_skipped() {
+        assert_eq!(
+            Encoding::from_accept(Some("text/html, application/json;q=0.9")),
+            Some(Encoding::Json)
+        );
+        assert_eq!(Encoding::from_accept(Some("text/html")), None);
+        assert_eq!(Encoding::from_accept(Some("text/html, image/png")), None);
+    }
+
+    #[test]
+    fn zero_quality_is_refused() {
+        assert_eq!(Encoding::from_accept(Some("application/json;q=0")), None);
+        assert_eq!(
+            Encoding::from_accept(Some("application/json;q=0, application/msgpack;q=0.2")),
+            Some(Encoding::MessagePack)
+        );
+    }
+
+    #[test]
+    fn invalid_quality_counts_as_one() {
+        assert_eq!(
+            Encoding::from_accept(Some("application/msgpack;q=0.5, application/json;q=high")),
+            Some(Encoding::Json)
+        );
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Binary {
+        #[serde(with = "super::bytes")]
+        data: Cow<'static, [u8]>,
+    }
+
+    #[test]
+    fn round_trip() {
+        let value = Binary {
+            data: Cow::Owned(vec![0, 1, 2, 255]),
+        };
+        for encoding in [Encoding::Bincode, Encoding::Json, Encoding::MessagePack] {
+            let bytes = encoding.encode(&value).unwrap();
+            assert_eq!(encoding.decode::<Binary>(&bytes).unwrap(), value);
+        }
+    }
+}
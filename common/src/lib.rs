@@ -1,22 +1,18 @@
-use std::borrow::Cow;
+pub mod api;
+pub mod bundle;
+pub mod encoding;
+pub mod project;
 
-use serde::{Deserialize, Serialize};
+/// The code that the editor starts with. The server also builds it at startup to warm up the
+/// workspace.
+pub static DEFAULT_CODE: &str = r#"use sycamore::prelude::*;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CompileRequest<'a> {
-    pub code: Cow<'a, str>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub enum CompileResponse<'a> {
-    Success {
-        js: Cow<'a, str>,
-        wasm: Cow<'a, [u8]>,
-    },
-    CompileError(String),
-}
+fn main() {
+    console_error_panic_hook::set_once();
+    console_log::init_with_level(log::Level::Debug).unwrap();
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PasteRequest<'a> {
-    pub code: Cow<'a, str>,
+    sycamore::render(|cx|
+        view! { cx, "Hello World!" }
+    );
 }
+"#;
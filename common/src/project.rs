@@ -0,0 +1,103 @@
+//! Exporting the playground as a project that builds with [trunk](https://trunkrs.dev).
+
+use std::io;
+
+use crate::api::Asset;
+use crate::bundle::{document, write_zip, PageHead};
+
+/// The manifest of the playground workspace. Exported projects use the same dependencies and
+/// profiles, so that code that builds in the playground builds in the project too.
+static PLAYGROUND_MANIFEST: &str = include_str!("../../playground/Cargo.toml");
+
+/// The name of the directory in the archive that contains the project.
+pub static PROJECT_DIR: &str = "playground";
+
+static GITIGNORE: &str = "/target\n/dist\n";
+
+static README: &str = r#"# Sycamore Playground project
+
+This project was exported from the [Sycamore Playground](https://sycamore-rs.github.io/playground).
+
+Build and serve it with [trunk](https://trunkrs.dev):
+
+```sh
+rustup target add wasm32-unknown-unknown
+cargo install trunk
+trunk serve --open
+```
+
+`trunk build --release` writes a version that can be hosted statically to `dist`.
+
+The markup and styles from the playground are in the `<head>` of `index.html`. If Tailwind was
+enabled, they use the Tailwind Play CDN, which is meant for prototyping. See
+https://tailwindcss.com/docs/installation for setting up Tailwind for production.
+"#;
+
+/// The files of a playground that are exported.
+#[derive(Debug, Clone)]
+pub struct Project<'a> {
+    pub code: &'a str,
+    pub assets: &'a [Asset<'a>],
+    pub head: &'a PageHead,
+}
+
+impl Project<'_> {
+    /// Packages the project into a zip archive. The project is in the [`PROJECT_DIR`] directory
+    /// of the archive, and the assets are in its root next to `index.html`.
+    pub fn to_zip(&self) -> io::Result<Vec<u8>> {
+        let cargo_toml = cargo_toml();
+        let index_html = self.index_html();
+        let generated = [
+            ("Cargo.toml", cargo_toml.as_bytes()),
+            ("index.html", index_html.as_bytes()),
+            ("src/main.rs", self.code.as_bytes()),
+            ("README.md", README.as_bytes()),
+            (".gitignore", GITIGNORE.as_bytes()),
+        ];
+        // An asset with the name of a generated file would replace it and break the project.
+        let assets = self
+            .assets
+            .iter()
+            .filter(|asset| generated.iter().all(|(name, _)| *name != asset.path))
+            .map(|asset| (asset.path.as_ref(), asset.data.as_ref()));
+        let files: Vec<_> = generated
+            .into_iter()
+            .chain(assets)
+            .map(|(path, data)| (format!("{PROJECT_DIR}/{path}"), data))
+            .collect();
+        write_zip(files.iter().map(|(path, data)| (path.as_str(), *data)))
+    }
+
+    /// The `index.html` that trunk builds the project from. Trunk copies the assets into the
+    /// output directory, so that the app can load them from the same relative URLs as in the
+    /// playground.
+    fn index_html(&self) -> String {
+        let mut links = vec![r#"<link data-trunk rel="rust" />"#.to_string()];
+        let mut dirs = Vec::new();
+        for asset in self.assets {
+            match asset.path.split_once('/') {
+                Some((dir, _)) if !dirs.contains(&dir) => {
+                    dirs.push(dir);
+                    links.push(format!(
+                        r#"<link data-trunk rel="copy-dir" href="{dir}" />"#
+                    ));
+                }
+                Some(_) => {}
+                None => links.push(format!(
+                    r#"<link data-trunk rel="copy-file" href="{}" />"#,
+                    asset.path
+                )),
+            }
+        }
+        document(self.head, None, &links.join("\n        "))
+    }
+}
+
+/// The manifest of an exported project.
+fn cargo_toml() -> String {
+    // Exported projects have no server that has to be kept in sync.
+    PLAYGROUND_MANIFEST.replace(
+        "# Must match the version of wasm-bindgen-cli-support in the server exactly.\n",
+        "# The version of wasm-bindgen that the playground builds with.\n",
+    )
+}
@@ -0,0 +1,74 @@
+//! Integrity checks of build artifacts.
+//!
+//! A wasm binary and JS glue code that do not belong together fail in the browser with obscure
+//! errors, if at all. They are therefore checked against each other before they are cached or
+//! returned.
+
+use std::collections::HashSet;
+
+use anyhow::{bail, Context, Result};
+use wasmparser::{Parser, Payload, Validator};
+
+/// The module that the JS glue code generated by wasm-bindgen provides the imports of the wasm
+/// binary in.
+const GLUE_IMPORT_MODULE: &str = "wbg";
+
+/// Checks that `wasm` is a valid wasm module, that it exports everything that the JS glue code
+/// uses, and that the JS glue code provides everything that it imports.
+pub fn check(wasm: &[u8], js: &str) -> Result<()> {
+    Validator::new()
+        .validate_all(wasm)
+        .context("the wasm binary is not valid")?;
+
+    let mut exports = HashSet::new();
+    let mut imports = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    exports.insert(export?.name);
+                }
+            }
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import?;
+                    if import.module == GLUE_IMPORT_MODULE {
+                        imports.push(import.name);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for name in glue_exports(js) {
+        if !exports.contains(name) {
+            bail!("the JS glue code uses `{name}`, which the wasm binary does not export");
+        }
+    }
+    for name in imports {
+        if !js.contains(&format!("imports.{GLUE_IMPORT_MODULE}.{name} =")) {
+            bail!("the wasm binary imports `{name}`, which the JS glue code does not provide");
+        }
+    }
+    Ok(())
+}
+
+/// The exports of the wasm binary that the JS glue code uses. The glue code accesses them as
+/// properties of a variable called `wasm`.
+fn glue_exports(js: &str) -> impl Iterator<Item = &str> {
+    js.match_indices("wasm.")
+        .filter_map(move |(start, prefix)| {
+            if js[..start].chars().next_back().is_some_and(is_ident_char) {
+                // Part of a longer identifier.
+                return None;
+            }
+            let rest = &js[start + prefix.len()..];
+            let end = rest.find(|c| !is_ident_char(c)).unwrap_or(rest.len());
+            (end > 0).then(|| &rest[..end])
+        })
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '$'
+}
@@ -0,0 +1,112 @@
+//! Serving the assets of successful builds.
+//!
+//! The preview sets the base URL of its document to the assets of its build, so that the app can
+//! load them from relative URLs.
+
+use std::io;
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::{header, HeaderValue};
+use axum::response::{IntoResponse, Response};
+use playground_common::api::ApiError;
+use tokio::fs;
+
+use crate::compile::cached_asset_path;
+use crate::config::Config;
+use crate::error::ErrorResponse;
+use crate::validate::{is_valid_asset_path, is_valid_code_hash};
+
+pub async fn get_asset(
+    Extension(config): Extension<Arc<Config>>,
+    Path((code_hash, path)): Path<(String, String)>,
+) -> Result<Response, ErrorResponse> {
+    let path = path.trim_start_matches('/');
+    if !is_valid_code_hash(&code_hash) || !is_valid_asset_path(path) {
+        return Err(ApiError::NotFound.into());
+    }
+    let data = match fs::read(cached_asset_path(&config, &code_hash, path)).await {
+        Ok(data) => data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Err(ApiError::NotFound.into()),
+        Err(err) => {
+            return Err(ApiError::Internal {
+                message: format!("could not read asset: {err}"),
+            }
+            .into())
+        }
+    };
+    Ok(asset_response(path, data))
+}
+
+/// Serves an asset with its content type. Assets are uploaded by users, so they must not be able
+/// to run script on the origin of the server if they are opened directly, e.g. an HTML or SVG file
+/// from a link. `sandbox` puts such documents into an opaque origin without scripts. It does not
+/// apply to assets that the preview loads as subresources, such as images or scripts.
+fn asset_response(path: &str, data: Vec<u8>) -> Response {
+    let headers = [
+        (
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(content_type(path)),
+        ),
+        (
+            header::CONTENT_SECURITY_POLICY,
+            HeaderValue::from_static("sandbox"),
+        ),
+        (
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        ),
+    ];
+    (headers, data).into_response()
+}
+
+/// Guesses the content type of a file from its extension.
+fn content_type(path: &str) -> &'static str {
+    let extension = path.rsplit_once('.').map(|(_, extension)| extension);
+    match extension.map(str::to_ascii_lowercase).as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("ico") => "image/x-icon",
+        Some("json") => "application/json",
+        Some("txt" | "md") => "text/plain; charset=utf-8",
+        Some("csv") => "text/csv; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("html") => "text/html; charset=utf-8",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("mp3") => "audio/mpeg",
+        Some("wav") => "audio/wav",
+        Some("mp4") => "video/mp4",
+        Some("wasm") => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn active_content_is_sandboxed() {
+        for path in ["index.html", "images/logo.svg", "script.js", "data.bin"] {
+            let res = asset_response(path, Vec::new());
+            let headers = res.headers();
+            assert_eq!(headers[header::CONTENT_SECURITY_POLICY], "sandbox");
+            assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        }
+    }
+
+    #[test]
+    fn content_type_from_extension() {
+        assert_eq!(content_type("a/b/logo.SVG"), "image/svg+xml");
+        assert_eq!(content_type("index.html"), "text/html; charset=utf-8");
+        assert_eq!(content_type("README"), "application/octet-stream");
+        assert_eq!(content_type("archive.tar.gz"), "application/octet-stream");
+    }
+}
@@ -0,0 +1,69 @@
+//! Serving successful builds as standalone bundles, see [`playground_common::bundle`].
+//!
+//! Bundles are built from the cache, so a bundle can only be downloaded as long as its build is
+//! cached.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use axum::extract::{Extension, Path};
+use axum::http::{header, HeaderValue};
+use axum::response::{IntoResponse, Response};
+use playground_common::api::{ApiError, CompileResponse};
+use playground_common::bundle::Bundle;
+
+use crate::compile::{read_cached_build, CachedBuild};
+use crate::config::Config;
+use crate::error::ErrorResponse;
+use crate::validate::is_valid_code_hash;
+
+pub async fn get_bundle(
+    Extension(config): Extension<Arc<Config>>,
+    Path(code_hash): Path<String>,
+) -> Result<Response, ErrorResponse> {
+    if !is_valid_code_hash(&code_hash) {
+        return Err(ApiError::NotFound.into());
+    }
+    let CachedBuild {
+        response,
+        assets,
+        head,
+    } = read_cached_build(&config, &code_hash)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    let CompileResponse::Success {
+        js, target, wasm, ..
+    } = response
+    else {
+        // Only successful builds are cached.
+        return Err(anyhow!("cached build is not a success").into());
+    };
+
+    let zip = tokio::task::spawn_blocking(move || {
+        Bundle {
+            js: &js,
+            target,
+            wasm: &wasm,
+            assets: &assets,
+            head: &head,
+        }
+        .to_zip()
+    })
+    .await
+    .context("bundling panicked")?
+    .context("could not build bundle")?;
+
+    let disposition = format!(r#"attachment; filename="playground-{code_hash}.zip""#);
+    let disposition = HeaderValue::from_str(&disposition).context("invalid file name")?;
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/zip"),
+            ),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        zip,
+    )
+        .into_response())
+}
@@ -0,0 +1,656 @@
+//! Compiling user code into a wasm binary and JS glue code.
+
+use std::collections::{hash_map::DefaultHasher, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::time::Instant;
+
+use anyhow::{anyhow, ensure, Context, Result};
+use once_cell::sync::Lazy;
+use playground_common::api::{Asset, CompileRequest, CompileResponse, JsTarget};
+use playground_common::bundle::PageHead;
+use playground_common::encoding::Encoding;
+use playground_common::DEFAULT_CODE;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::{fs, sync::Mutex};
+use tracing::{debug, info, Span};
+use wasm_bindgen_cli_support::Bindgen;
+
+use crate::artifact;
+use crate::config::Config;
+use crate::jobs::{Job, JobState};
+use crate::metrics::{GaugeGuard, METRICS};
+use crate::testing;
+
+/// The encoding of the compile results that are stored in the cache. Responses with a different
+/// encoding are re-encoded on the fly.
+pub const CACHE_ENCODING: Encoding = Encoding::Bincode;
+
+/// Hashes the code, assets and head of a compile request. Requests with the same hash produce the
+/// same build.
+pub fn hash_request(code: &str, assets: &[Asset], head: &PageHead) -> String {
+    let mut hasher = DefaultHasher::new();
+    code.hash(&mut hasher);
+    for asset in assets {
+        asset.path.hash(&mut hasher);
+        asset.data.hash(&mut hasher);
+    }
+    head.hash(&mut hasher);
+    let hash = hasher.finish();
+    base64::encode_config(hash.to_le_bytes(), base64::URL_SAFE)
+}
+
+/// The code hashes of the compile results that are stored in the cache, oldest first.
+#[derive(Default)]
+struct CacheIndex {
+    entries: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl CacheIndex {
+    fn contains(&self, code_hash: &str) -> bool {
+        self.entries.contains(code_hash)
+    }
+
+    /// Adds an entry to the cache. Returns the entries that were evicted to make room for it.
+    fn insert(&mut self, code_hash: String, max_entries: usize) -> Vec<String> {
+        let mut evicted = Vec::new();
+        if self.entries.insert(code_hash.clone()) {
+            self.order.push_back(code_hash);
+        }
+        while self.order.len() > max_entries {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+                evicted.push(oldest);
+            }
+        }
+        evicted
+    }
+}
+
+/// The code hashes of the builds in the cache.
+static CACHE: Lazy<Mutex<CacheIndex>> = Lazy::new(Default::default);
+
+/// Fills the cache index with the entries that previous runs of the server left in the cache
+/// directory, oldest first by modification time, and evicts the oldest ones beyond
+/// `max_entries`. Called at startup, before any compile.
+pub async fn load_cache(config: &Config) -> io::Result<()> {
+    let mut entries = Vec::new();
+    let mut dir = fs::read_dir(&config.cache.dir).await?;
+    while let Some(entry) = dir.next_entry().await? {
+        // Entries are moved into place once they are complete, so every directory with a response
+        // is a complete entry. This also skips the build directories.
+        let Ok(metadata) = fs::metadata(entry.path().join("response.bin")).await else {
+            continue;
+        };
+        let Ok(code_hash) = entry.file_name().into_string() else {
+            continue;
+        };
+        entries.push((metadata.modified()?, code_hash));
+    }
+    entries.sort();
+
+    let mut cache = CACHE.lock().await;
+    let mut evicted = Vec::new();
+    for (_, code_hash) in entries {
+        evicted.extend(cache.insert(code_hash, config.cache.max_entries));
+    }
+    for code_hash in &evicted {
+        fs::remove_dir_all(cache_entry_dir(config, code_hash)).await?;
+    }
+    info!(
+        entries = cache.entries.len(),
+        evicted = evicted.len(),
+        "loaded the cache"
+    );
+    Ok(())
+}
+
+/// The directory of a cache entry. Contains the serialized `CompileResponse` and the assets of
+/// the build.
+fn cache_entry_dir(config: &Config, code_hash: &str) -> PathBuf {
+    config.cache.dir.join(code_hash)
+}
+
+fn cache_file_name(config: &Config, code_hash: &str) -> PathBuf {
+    cache_entry_dir(config, code_hash).join("response.bin")
+}
+
+/// The file that the head of a cached build is stored in, encoded with [`CACHE_ENCODING`].
+fn cached_head_file_name(config: &Config, code_hash: &str) -> PathBuf {
+    cache_entry_dir(config, code_hash).join("head.bin")
+}
+
+/// Where an asset of a cached build is stored. The path has to be
+/// [valid](crate::validate::is_valid_asset_path).
+pub fn cached_asset_path(config: &Config, code_hash: &str, path: &str) -> PathBuf {
+    cache_entry_dir(config, code_hash).join("assets").join(path)
+}
+
+/// A build that was read from the cache.
+pub struct CachedBuild {
+    pub response: CompileResponse<'static>,
+    pub assets: Vec<Asset<'static>>,
+    pub head: PageHead,
+}
+
+/// Reads the result, the assets and the head of a cached build. Returns `None` if the build is not
+/// cached.
+pub async fn read_cached_build(config: &Config, code_hash: &str) -> Result<Option<CachedBuild>> {
+    let bytes = match fs::read(cache_file_name(config, code_hash)).await {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).context("Could not read cache file."),
+    };
+    let response = CACHE_ENCODING
+        .decode::<CompileResponse>(&bytes)
+        .context("Could not deserialize cache file.")?
+        .into_owned();
+    let head = fs::read(cached_head_file_name(config, code_hash))
+        .await
+        .context("Could not read cache file.")?;
+    let head = CACHE_ENCODING
+        .decode::<PageHead>(&head)
+        .context("Could not deserialize cache file.")?;
+
+    let assets_dir = cache_entry_dir(config, code_hash).join("assets");
+    let mut assets = Vec::new();
+    let mut dirs = vec![assets_dir.clone()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = fs::read_dir(&dir).await.context("Could not read assets.")?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if entry.file_type().await?.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            // Assets are validated before they are stored, so their paths are valid UTF-8.
+            let relative = path
+                .strip_prefix(&assets_dir)?
+                .to_string_lossy()
+                .into_owned();
+            let data = fs::read(&path).await.context("Could not read asset.")?;
+            assets.push(Asset {
+                path: relative.into(),
+                data: data.into(),
+            });
+        }
+    }
+    // Keeps the bundles of the same build identical.
+    assets.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(Some(CachedBuild {
+        response,
+        assets,
+        head,
+    }))
+}
+
+/// cargo was killed while building the workspace, which may have left the workspace in a broken
+/// state.
+#[derive(Debug)]
+pub struct CargoCrashed(pub ExitStatus);
+
+impl fmt::Display for CargoCrashed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cargo crashed: {}", self.0)
+    }
+}
+
+impl std::error::Error for CargoCrashed {}
+
+/// Whether a compile job failed because cargo crashed, so that the workspace has to be warmed up
+/// again. Other failures, e.g. because the cache could not be written, leave the workspace intact.
+pub fn needs_warm_up(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<CargoCrashed>().is_some()
+}
+
+/// Held while the workspace is being built, so that only one build runs at a time.
+pub static WORKSPACE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+fn cargo_build_command(workspace: &Path) -> Command {
+    let mut command = Command::new("cargo");
+    command
+        .env_remove("GITHUB_TOKEN")
+        .arg("build")
+        .arg("--target")
+        .arg("wasm32-unknown-unknown")
+        .current_dir(workspace);
+    command
+}
+
+/// The wasm binary that cargo builds in the workspace.
+fn cargo_output_path(workspace: &Path) -> PathBuf {
+    workspace.join("target/wasm32-unknown-unknown/debug/playground.wasm")
+}
+
+/// Removes the wasm binary of the previous build, so that it can never be mistaken for the output
+/// of the current one.
+async fn remove_cargo_output(workspace: &Path) -> io::Result<()> {
+    match fs::remove_file(cargo_output_path(workspace)).await {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// The directory that holds the build directories of running builds.
+fn build_dirs(config: &Config) -> PathBuf {
+    config.cache.dir.join("builds")
+}
+
+/// Removes build directories left behind by a previous run of the server.
+pub async fn remove_build_dirs(config: &Config) -> io::Result<()> {
+    match fs::remove_dir_all(build_dirs(config)).await {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// A directory that only a single build writes to. Removed when dropped.
+///
+/// cargo has to build in the shared workspace to reuse the compiled dependencies. Everything after
+/// that happens in the build directory, so that the next build can start in the workspace and
+/// interrupted builds cannot leave behind artifacts that are picked up by another build.
+pub struct BuildDir(PathBuf);
+
+impl BuildDir {
+    pub async fn create(config: &Config, id: &str) -> io::Result<Self> {
+        let path = build_dirs(config).join(id);
+        fs::create_dir_all(&path).await?;
+        Ok(Self(path))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for BuildDir {
+    fn drop(&mut self) {
+        let path = std::mem::take(&mut self.0);
+        // Removing a large directory blocks, so it is done on the blocking pool rather than on a
+        // worker of the runtime.
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn_blocking(move || std::fs::remove_dir_all(path));
+            }
+            Err(_) => {
+                let _ = std::fs::remove_dir_all(path);
+            }
+        }
+    }
+}
+
+/// Runs wasm-bindgen on a wasm binary built by cargo. Returns the processed wasm binary and its
+/// JS glue code, or the error reported by wasm-bindgen.
+async fn bindgen(input: &Path, target: JsTarget) -> Result<Result<(Vec<u8>, String), String>> {
+    let input = fs::read(input)
+        .await
+        .context("could not read the wasm binary built by cargo")?;
+    // wasm-bindgen is CPU bound and can take a few seconds for large binaries.
+    let output = tokio::task::spawn_blocking(move || -> Result<_> {
+        let mut bindgen = Bindgen::new();
+        bindgen.input_bytes("playground", input);
+        match target {
+            JsTarget::Web => bindgen.web(true)?,
+            JsTarget::NoModules => bindgen.no_modules(true)?,
+        };
+        let mut output = bindgen.generate_output()?;
+        // The response has no room for additional JS files.
+        ensure!(
+            output.snippets().values().all(Vec::is_empty) && output.local_modules().is_empty(),
+            "JS snippets (`inline_js` and `module` in `#[wasm_bindgen]`) are not supported"
+        );
+        Ok((output.wasm_mut().emit_wasm(), output.js().to_string()))
+    })
+    .await?;
+    // Formatted like the errors of the wasm-bindgen CLI.
+    Ok(output.map_err(|err| format!("error: {err:?}")))
+}
+
+/// Builds [`DEFAULT_CODE`] in the workspace, so that the dependencies are compiled before the
+/// first request comes in.
+pub async fn warm_up(config: &Config) -> Result<()> {
+    let _guard = WORKSPACE_LOCK.lock().await;
+    let workspace = &config.workspace;
+    fs::write(workspace.join("src/main.rs"), DEFAULT_CODE).await?;
+    remove_cargo_output(workspace).await?;
+
+    let cargo_build = run(cargo_build_command(workspace))
+        .await
+        .context("call cargo")?;
+    ensure!(
+        cargo_build.status.success(),
+        "cargo build failed with {}: {}",
+        cargo_build.status,
+        String::from_utf8_lossy(&cargo_build.stderr)
+    );
+    let (wasm, js) = bindgen(&cargo_output_path(workspace), config.js_target)
+        .await?
+        .map_err(|err| anyhow!("wasm-bindgen failed: {err}"))?;
+    artifact::check(&wasm, &js)?;
+    if config.enable_tests {
+        testing::warm_up(workspace).await?;
+    }
+    Ok(())
+}
+
+/// Runs a compile job: compiles the code and stores the result in a cache. Returns a
+/// `CompileResponse` serialized with [`CACHE_ENCODING`]. The progress of the build is reported
+/// to `job`.
+/// If the code has already been compiled and is found in the cache, returns the cached binary instead of recompiling.
+#[tracing::instrument(skip_all, fields(code_hash))]
+pub async fn process_compile(
+    config: &Config,
+    CompileRequest { code, assets, head }: CompileRequest<'_>,
+    job: &Job,
+) -> Result<Vec<u8>> {
+    let code_hash = hash_request(&code, &assets, &head);
+    Span::current().record("code_hash", code_hash.as_str());
+    let cache_file_name = cache_file_name(config, &code_hash);
+    // First check if we have a cached version.
+    if CACHE.lock().await.contains(&code_hash) {
+        METRICS.cache_lookups.with_label_values(&["hit"]).inc();
+        info!(cache = "hit", "serving cached result");
+        // Deserialize the cached file into a `CompileResponse`.
+        let res = fs::read(cache_file_name).await?;
+        // Only successful builds are cached.
+        METRICS.compiles.with_label_values(&["success"]).inc();
+        // Return the cached file.
+        return Ok(res);
+    }
+    METRICS.cache_lookups.with_label_values(&["miss"]).inc();
+    info!(cache = "miss", "compiling");
+
+    // Acquire the lock to prevent multiple requests from compiling at the same time.
+    let queued = GaugeGuard::new(&METRICS.queue_depth);
+    let lock_wait = Instant::now();
+    let guard = WORKSPACE_LOCK.lock().await;
+    let lock_wait = lock_wait.elapsed();
+    METRICS.lock_wait.observe(lock_wait.as_secs_f64());
+    debug!(
+        lock_wait_ms = lock_wait.as_millis() as u64,
+        "acquired workspace"
+    );
+    drop(queued);
+    job.set_state(JobState::Building);
+
+    let workspace = &config.workspace;
+    fs::write(workspace.join("src/main.rs"), code.as_bytes()).await?;
+    remove_cargo_output(workspace).await?;
+
+    let cargo_start = Instant::now();
+    let cargo_build = run(cargo_build_command(workspace))
+        .await
+        .context("call cargo")?;
+    let cargo_duration = cargo_start.elapsed();
+    METRICS
+        .phase_duration
+        .with_label_values(&["cargo"])
+        .observe(cargo_duration.as_secs_f64());
+    info!(
+        phase = "cargo",
+        duration_ms = cargo_duration.as_millis() as u64,
+        exit_code = cargo_build.status.code(),
+        "cargo finished"
+    );
+    // cargo only exits without a code if it was killed, e.g. by the OOM killer. That is a crash
+    // rather than a compile error.
+    if cargo_build.status.code().is_none() {
+        return Err(CargoCrashed(cargo_build.status).into());
+    }
+
+    if cargo_build.status.success() {
+        let build_dir = BuildDir::create(config, job.id()).await?;
+        let input = build_dir.path().join("playground.wasm");
+        fs::copy(cargo_output_path(workspace), &input)
+            .await
+            .context("could not copy the wasm binary built by cargo")?;
+        // The workspace is not needed anymore.
+        drop(guard);
+
+        job.set_state(JobState::Bundling);
+        let bindgen_start = Instant::now();
+        let bindgen_output = bindgen(&input, config.js_target).await;
+        let bindgen_duration = bindgen_start.elapsed();
+        METRICS
+            .phase_duration
+            .with_label_values(&["bindgen"])
+            .observe(bindgen_duration.as_secs_f64());
+        info!(
+            phase = "bindgen",
+            duration_ms = bindgen_duration.as_millis() as u64,
+            success = matches!(bindgen_output, Ok(Ok(_))),
+            "wasm-bindgen finished"
+        );
+        let (wasm, js) = match bindgen_output? {
+            Ok(output) => output,
+            Err(err) => {
+                // Like compile errors, bundle errors are not cached.
+                let bytes = CACHE_ENCODING.encode(&CompileResponse::BundleError(err))?;
+                METRICS.compiles.with_label_values(&["bundle_error"]).inc();
+                return Ok(bytes);
+            }
+        };
+        artifact::check(&wasm, &js).context("wasm-bindgen produced a broken artifact")?;
+
+        // Serialize the generated artifacts into a `CompileResponse`.
+        METRICS
+            .artifact_size
+            .with_label_values(&["wasm"])
+            .observe(wasm.len() as f64);
+        METRICS
+            .artifact_size
+            .with_label_values(&["js"])
+            .observe(js.len() as f64);
+        let res = CompileResponse::Success {
+            wasm: wasm.into(),
+            js: js.into(),
+            target: config.js_target,
+            code_hash: code_hash.clone(),
+        };
+        let bytes = CACHE_ENCODING
+            .encode(&res)
+            .context("Could not serialize result.")?;
+
+        // Add the generated file and the assets to the cache. The entry is moved into place so
+        // that the cache never contains a partially written entry.
+        let entry = build_dir.path().join("entry");
+        fs::create_dir_all(entry.join("assets")).await?;
+        fs::write(entry.join("response.bin"), &bytes)
+            .await
+            .context("Could not write cache file.")?;
+        fs::write(entry.join("head.bin"), CACHE_ENCODING.encode(&head)?)
+            .await
+            .context("Could not write cache file.")?;
+        for asset in &assets {
+            let path = entry.join("assets").join(&*asset.path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            fs::write(path, &asset.data)
+                .await
+                .context("Could not write asset.")?;
+        }
+        let cache_entry_dir = cache_entry_dir(config, &code_hash);
+        if let Err(err) = fs::rename(&entry, &cache_entry_dir).await {
+            // Another job may have cached the same build in the meantime.
+            if !cache_entry_dir.is_dir() {
+                return Err(err).context("Could not write cache file.");
+            }
+        }
+        let evicted = CACHE
+            .lock()
+            .await
+            .insert(code_hash, config.cache.max_entries);
+        for code_hash in evicted {
+            let _ = fs::remove_dir_all(self::cache_entry_dir(config, &code_hash)).await;
+        }
+
+        METRICS.compiles.with_label_values(&["success"]).inc();
+        Ok(bytes)
+    } else {
+        // Compile error. We don't want to return `Err(_)` because we want to serialize the error into a `CompileResponse`.
+        let res =
+            CompileResponse::CompileError(String::from_utf8_lossy(&cargo_build.stderr).to_string());
+        let bytes = CACHE_ENCODING.encode(&res)?;
+        METRICS.compiles.with_label_values(&["compile_error"]).inc();
+        Ok(bytes)
+    }
+}
+
+/// Kills a process group when dropped, unless it has been disarmed.
+struct KillProcessGroupOnDrop(Option<u32>);
+
+impl Drop for KillProcessGroupOnDrop {
+    fn drop(&mut self) {
+        if let Some(pgid) = self.0 {
+            kill_process_group(pgid);
+        }
+    }
+}
+
+fn kill_process_group(pgid: u32) {
+    // SAFETY: `kill` has no memory safety requirements. At worst, the process group has already
+    // exited and the call fails with `ESRCH`.
+    unsafe {
+        libc::kill(-(pgid as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+/// Runs the command in a new process group and collects its output.
+///
+/// If the returned future is dropped before the command exits, because the compile job was
+/// cancelled or timed out, the whole process group is killed. This makes sure that no `rustc`
+/// processes spawned by cargo keep running and holding on to the workspace.
+pub async fn run(mut command: Command) -> io::Result<Output> {
+    command
+        .process_group(0)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let child = tokio::process::Command::from(command)
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut guard = KillProcessGroupOnDrop(child.id());
+    let output = child.wait_with_output().await?;
+    guard.0 = None;
+    Ok(output)
+}
+
+/// Like [`run`], but kills the process group as soon as the command writes more than `limit` bytes
+/// to stdout or stderr, and returns `None` in that case. For running user code, which may print
+/// without end.
+pub async fn run_with_output_limit(mut command: Command, limit: u64) -> io::Result<Option<Output>> {
+    command
+        .process_group(0)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = tokio::process::Command::from(command)
+        .kill_on_drop(true)
+        .spawn()?;
+    let pgid = child.id();
+    let mut guard = KillProcessGroupOnDrop(pgid);
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let (stdout, stderr) = tokio::try_join!(
+        read_up_to(stdout, limit, pgid),
+        read_up_to(stderr, limit, pgid)
+    )?;
+    let status = child.wait().await?;
+    guard.0 = None;
+    if stdout.len() as u64 > limit || stderr.len() as u64 > limit {
+        return Ok(None);
+    }
+    Ok(Some(Output {
+        status,
+        stdout,
+        stderr,
+    }))
+}
+
+/// Reads a pipe until it is closed or more than `limit` bytes have been read. In the latter case,
+/// the process group is killed, since it would block on the full pipe otherwise.
+async fn read_up_to(
+    pipe: impl AsyncRead + Unpin,
+    limit: u64,
+    pgid: Option<u32>,
+) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    pipe.take(limit + 1).read_to_end(&mut data).await?;
+    if data.len() as u64 > limit {
+        if let Some(pgid) = pgid {
+            kill_process_group(pgid);
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[test]
+    fn only_crashed_cargo_needs_warm_up() {
+        // Killed by SIGKILL, e.g. by the OOM killer.
+        let crashed = anyhow::Error::from(CargoCrashed(ExitStatus::from_raw(9)));
+        assert!(needs_warm_up(&crashed));
+        assert!(needs_warm_up(&crashed.context("compile job failed")));
+
+        let io_error = io::Error::new(io::ErrorKind::PermissionDenied, "cache is read-only");
+        assert!(!needs_warm_up(&anyhow::Error::from(io_error)));
+        assert!(!needs_warm_up(&anyhow!("could not copy the wasm binary")));
+    }
+
+    #[tokio::test]
+    async fn output_is_limited() {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("echo out; echo err >&2");
+        let output = run_with_output_limit(command, 16).await.unwrap().unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+
+        // Would print forever, and keeps stderr open.
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("yes");
+        assert!(run_with_output_limit(command, 1024)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn head_is_part_of_the_hash() {
+        let code = "fn main() {}";
+        let head = PageHead::default();
+        assert_eq!(
+            hash_request(code, &[], &head),
+            hash_request(code, &[], &head.clone())
+        );
+        let styled = PageHead {
+            css: "body { margin: 0; }".to_string(),
+            ..PageHead::default()
+        };
+        assert_ne!(
+            hash_request(code, &[], &head),
+            hash_request(code, &[], &styled)
+        );
+        let tailwind = PageHead {
+            tailwind: true,
+            ..PageHead::default()
+        };
+        assert_ne!(
+            hash_request(code, &[], &head),
+            hash_request(code, &[], &tailwind)
+        );
+    }
+}
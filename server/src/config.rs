@@ -0,0 +1,494 @@
+//! Server configuration.
+//!
+//! The configuration is assembled from, in increasing order of precedence: built-in defaults, an
+//! optional TOML file, environment variables and command-line flags. It is validated once at
+//! startup so that misconfigurations are reported before the server starts accepting requests.
+
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context, Result};
+use axum::http::HeaderValue;
+use clap::{Parser, ValueEnum};
+use playground_common::api::JsTarget;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The address to listen on.
+    pub bind: SocketAddr,
+    /// The number of worker threads of the async runtime. Defaults to the number of CPU cores.
+    pub workers: Option<usize>,
+    /// Path to the cargo workspace that user code is compiled in.
+    pub workspace: PathBuf,
+    /// The kind of JS glue code that is generated for compiled code.
+    pub js_target: JsTarget,
+    /// The format of log messages. The log level is set with the `RUST_LOG` environment variable.
+    pub log_format: LogFormat,
+    /// Origins that are allowed to make cross-origin requests. All origins are allowed if empty.
+    pub cors_origins: Vec<String>,
+    /// Maximum time in seconds that a request may take before it is aborted.
+    pub request_timeout_secs: u64,
+    /// Maximum time in seconds that a compile job may take before the build is killed, not counting
+    /// the time it waits for other builds. Also limits test runs.
+    pub compile_timeout_secs: u64,
+    /// Maximum time in seconds that importing a project may take. Imports fetch every file of the
+    /// project from GitHub, so they take longer than other requests.
+    pub import_timeout_secs: u64,
+    /// Run the tests in submitted code.
+    ///
+    /// **Security:** unlike compiling, this executes the submitted code. The test binaries and
+    /// Node.js run in the sandbox configured in [`SandboxConfig`], which is required, as another
+    /// user, without network access and with a read-only view of the file system.
+    pub enable_tests: bool,
+    pub sandbox: SandboxConfig,
+    pub cache: CacheConfig,
+    pub paste: PasteConfig,
+    pub rate_limit: RateLimitConfig,
+    pub limits: LimitsConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// The directory that compiled artifacts are cached in.
+    pub dir: PathBuf,
+    /// The maximum number of compiled artifacts to keep. The oldest artifacts are evicted first.
+    pub max_entries: usize,
+}
+
+/// The sandbox that tests run in.
+///
+/// Test binaries and Node.js run in [bubblewrap](https://github.com/containers/bubblewrap) as
+/// `uid` and `gid`, which must be an unprivileged user other than the one of the server. They get
+/// no network, a read-only view of the file system without the workspace and the cache, and a
+/// scratch `/tmp`. The server switches to the user before starting bubblewrap, so it has to be
+/// allowed to, e.g. by running as root, and the kernel has to allow unprivileged user namespaces.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// The path of the `bwrap` binary.
+    pub bwrap: Option<PathBuf>,
+    /// The user that tests run as.
+    pub uid: Option<u32>,
+    /// The group that tests run as.
+    pub gid: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PasteConfig {
+    pub backend: PasteBackend,
+    /// The token used to create gists, and for importing repositories if set. Usually set with the
+    /// `GITHUB_TOKEN` environment variable rather than in the configuration file.
+    pub github_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable text.
+    Pretty,
+    /// One JSON object per line.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum PasteBackend {
+    /// Store pastes as GitHub gists.
+    Github,
+    /// Disable sharing through the server.
+    Disabled,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// The maximum size of the code in a compile or paste request, in bytes.
+    pub max_code_bytes: u64,
+    /// The maximum size of a request body, in bytes.
+    pub max_payload_bytes: u64,
+    /// The maximum number of files in a compile request, counting the code and every asset.
+    pub max_files: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// Use the last address in the `X-Forwarded-For` header as the client IP. Only enable this
+    /// behind a reverse proxy that sets the header, otherwise clients can pick their own IP.
+    pub trust_proxy: bool,
+    /// API keys that clients can send in the API key header to be rate limited per key instead
+    /// of per IP address.
+    pub api_keys: Vec<String>,
+    /// IP addresses and API keys that are not rate limited.
+    pub allow_list: Vec<String>,
+    /// Limits requests that start a build.
+    pub compile: BucketConfig,
+    /// Limits requests that create or fetch gists, or import repositories.
+    pub paste: BucketConfig,
+}
+
+/// The configuration of a token bucket. Every client starts with `burst` tokens and regains
+/// `per_minute` tokens per minute, up to `burst`. Each request takes one token.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BucketConfig {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from(([0, 0, 0, 0], 3000)),
+            workers: None,
+            workspace: PathBuf::from("../playground"),
+            js_target: JsTarget::Web,
+            log_format: LogFormat::Pretty,
+            cors_origins: Vec::new(),
+            request_timeout_secs: 4,
+            compile_timeout_secs: 300,
+            import_timeout_secs: 30,
+            enable_tests: false,
+            sandbox: SandboxConfig::default(),
+            cache: CacheConfig::default(),
+            paste: PasteConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            limits: LimitsConfig::default(),
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("cache"),
+            max_entries: 1000,
+        }
+    }
+}
+
+impl Default for PasteConfig {
+    fn default() -> Self {
+        Self {
+            backend: PasteBackend::Github,
+            github_token: None,
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_code_bytes: 100 * 1024,
+            // Leaves room for assets, which are base64 encoded.
+            max_payload_bytes: 1024 * 1024,
+            max_files: 20,
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            trust_proxy: false,
+            api_keys: Vec::new(),
+            allow_list: Vec::new(),
+            compile: BucketConfig {
+                burst: 10,
+                per_minute: 10,
+            },
+            paste: BucketConfig {
+                burst: 10,
+                per_minute: 5,
+            },
+        }
+    }
+}
+
+/// Sycamore playground compiler service.
+///
+/// Every flag can also be set with the environment variable shown in its description, or in the
+/// TOML configuration file.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// Path to a TOML configuration file.
+    #[arg(long, env = "PLAYGROUND_CONFIG")]
+    config: Option<PathBuf>,
+    /// The IP address to listen on.
+    #[arg(long, env = "PLAYGROUND_HOST")]
+    host: Option<IpAddr>,
+    /// The port to listen on.
+    #[arg(long, env = "PORT")]
+    port: Option<u16>,
+    /// The number of worker threads.
+    #[arg(long, env = "PLAYGROUND_WORKERS")]
+    workers: Option<usize>,
+    /// Path to the cargo workspace that user code is compiled in.
+    #[arg(long, env = "PLAYGROUND_WORKSPACE")]
+    workspace: Option<PathBuf>,
+    /// The kind of JS glue code to generate: `web` or `no-modules`.
+    #[arg(long, env = "PLAYGROUND_JS_TARGET")]
+    js_target: Option<JsTarget>,
+    /// The format of log messages.
+    #[arg(long, env = "PLAYGROUND_LOG_FORMAT")]
+    log_format: Option<LogFormat>,
+    /// Comma separated list of origins that are allowed to make cross-origin requests.
+    #[arg(long, env = "PLAYGROUND_CORS_ORIGINS", value_delimiter = ',')]
+    cors_origins: Option<Vec<String>>,
+    /// Maximum time in seconds that a request may take.
+    #[arg(long, env = "PLAYGROUND_REQUEST_TIMEOUT")]
+    request_timeout: Option<u64>,
+    /// Maximum time in seconds that a compile job may take.
+    #[arg(long, env = "PLAYGROUND_COMPILE_TIMEOUT")]
+    compile_timeout: Option<u64>,
+    /// Maximum time in seconds that importing a project may take.
+    #[arg(long, env = "PLAYGROUND_IMPORT_TIMEOUT")]
+    import_timeout: Option<u64>,
+    /// Run the tests in submitted code. Requires the sandbox to be configured.
+    #[arg(long, env = "PLAYGROUND_ENABLE_TESTS")]
+    enable_tests: bool,
+    /// The path of the bubblewrap binary that tests run in.
+    #[arg(long, env = "PLAYGROUND_SANDBOX_BWRAP")]
+    sandbox_bwrap: Option<PathBuf>,
+    /// The user that tests run as.
+    #[arg(long, env = "PLAYGROUND_SANDBOX_UID")]
+    sandbox_uid: Option<u32>,
+    /// The group that tests run as.
+    #[arg(long, env = "PLAYGROUND_SANDBOX_GID")]
+    sandbox_gid: Option<u32>,
+    /// The directory that compiled artifacts are cached in.
+    #[arg(long, env = "PLAYGROUND_CACHE_DIR")]
+    cache_dir: Option<PathBuf>,
+    /// The maximum number of compiled artifacts to keep.
+    #[arg(long, env = "PLAYGROUND_CACHE_MAX_ENTRIES")]
+    cache_max_entries: Option<usize>,
+    /// Where shared code snippets are stored.
+    #[arg(long, env = "PLAYGROUND_PASTE_BACKEND")]
+    paste_backend: Option<PasteBackend>,
+    /// The token used to create gists.
+    #[arg(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+    github_token: Option<String>,
+    /// The maximum size of submitted code in bytes.
+    #[arg(long, env = "PLAYGROUND_MAX_CODE_BYTES")]
+    max_code_bytes: Option<u64>,
+    /// The maximum size of a request body in bytes.
+    #[arg(long, env = "PLAYGROUND_MAX_PAYLOAD_BYTES")]
+    max_payload_bytes: Option<u64>,
+    /// The maximum number of files in a compile request.
+    #[arg(long, env = "PLAYGROUND_MAX_FILES")]
+    max_files: Option<usize>,
+    /// Disable rate limiting.
+    #[arg(long, env = "PLAYGROUND_NO_RATE_LIMIT")]
+    no_rate_limit: bool,
+    /// Identify clients by the last address in `X-Forwarded-For`. Only use this behind a reverse
+    /// proxy that sets the header.
+    #[arg(long, env = "PLAYGROUND_TRUST_PROXY")]
+    trust_proxy: bool,
+    /// Comma separated list of API keys that are rate limited per key instead of per IP address.
+    #[arg(
+        long,
+        env = "PLAYGROUND_API_KEYS",
+        value_delimiter = ',',
+        hide_env_values = true
+    )]
+    api_keys: Option<Vec<String>>,
+    /// Comma separated list of IP addresses and API keys that are not rate limited.
+    #[arg(
+        long,
+        env = "PLAYGROUND_RATE_LIMIT_ALLOW_LIST",
+        value_delimiter = ',',
+        hide_env_values = true
+    )]
+    rate_limit_allow_list: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Loads the configuration from the configuration file, environment variables and
+    /// command-line flags, and validates it.
+    pub fn load() -> Result<Self> {
+        let args = Args::parse();
+
+        let mut config = match &args.config {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("could not read config file {}", path.display()))?;
+                toml::from_str(&text)
+                    .with_context(|| format!("invalid config file {}", path.display()))?
+            }
+            None => Config::default(),
+        };
+
+        if let Some(host) = args.host {
+            config.bind.set_ip(host);
+        }
+        if let Some(port) = args.port {
+            config.bind.set_port(port);
+        }
+        if let Some(workers) = args.workers {
+            config.workers = Some(workers);
+        }
+        if let Some(workspace) = args.workspace {
+            config.workspace = workspace;
+        }
+        if let Some(js_target) = args.js_target {
+            config.js_target = js_target;
+        }
+        if let Some(log_format) = args.log_format {
+            config.log_format = log_format;
+        }
+        if let Some(cors_origins) = args.cors_origins {
+            config.cors_origins = cors_origins;
+        }
+        if let Some(request_timeout) = args.request_timeout {
+            config.request_timeout_secs = request_timeout;
+        }
+        if let Some(compile_timeout) = args.compile_timeout {
+            config.compile_timeout_secs = compile_timeout;
+        }
+        if let Some(import_timeout) = args.import_timeout {
+            config.import_timeout_secs = import_timeout;
+        }
+        if args.enable_tests {
+            config.enable_tests = true;
+        }
+        if let Some(bwrap) = args.sandbox_bwrap {
+            config.sandbox.bwrap = Some(bwrap);
+        }
+        if let Some(uid) = args.sandbox_uid {
+            config.sandbox.uid = Some(uid);
+        }
+        if let Some(gid) = args.sandbox_gid {
+            config.sandbox.gid = Some(gid);
+        }
+        if let Some(cache_dir) = args.cache_dir {
+            config.cache.dir = cache_dir;
+        }
+        if let Some(cache_max_entries) = args.cache_max_entries {
+            config.cache.max_entries = cache_max_entries;
+        }
+        if let Some(paste_backend) = args.paste_backend {
+            config.paste.backend = paste_backend;
+        }
+        if let Some(github_token) = args.github_token {
+            config.paste.github_token = Some(github_token);
+        }
+        if let Some(max_code_bytes) = args.max_code_bytes {
+            config.limits.max_code_bytes = max_code_bytes;
+        }
+        if let Some(max_payload_bytes) = args.max_payload_bytes {
+            config.limits.max_payload_bytes = max_payload_bytes;
+        }
+        if let Some(max_files) = args.max_files {
+            config.limits.max_files = max_files;
+        }
+        if args.no_rate_limit {
+            config.rate_limit.enabled = false;
+        }
+        if args.trust_proxy {
+            config.rate_limit.trust_proxy = true;
+        }
+        if let Some(api_keys) = args.api_keys {
+            config.rate_limit.api_keys = api_keys;
+        }
+        if let Some(allow_list) = args.rate_limit_allow_list {
+            config.rate_limit.allow_list = allow_list;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        ensure!(
+            self.workers != Some(0),
+            "the number of workers must be at least 1"
+        );
+        ensure!(
+            self.request_timeout_secs > 0,
+            "the request timeout must be at least 1 second"
+        );
+        ensure!(
+            self.compile_timeout_secs > 0,
+            "the compile timeout must be at least 1 second"
+        );
+        ensure!(
+            self.import_timeout_secs > 0,
+            "the import timeout must be at least 1 second"
+        );
+        if self.enable_tests {
+            let (Some(_), Some(uid), Some(gid)) =
+                (&self.sandbox.bwrap, self.sandbox.uid, self.sandbox.gid)
+            else {
+                bail!("running tests requires the sandbox binary, uid and gid to be configured");
+            };
+            // SAFETY: `geteuid` has no memory safety requirements.
+            let server_uid = unsafe { libc::geteuid() };
+            ensure!(
+                uid != 0 && gid != 0 && uid != server_uid,
+                "tests must run as an unprivileged user other than the one of the server"
+            );
+        }
+        ensure!(
+            self.cache.max_entries > 0,
+            "the cache must hold at least 1 entry"
+        );
+        ensure!(
+            self.workspace.join("Cargo.toml").is_file(),
+            "the workspace {} does not contain a Cargo.toml",
+            self.workspace.display()
+        );
+        std::fs::create_dir_all(&self.cache.dir).with_context(|| {
+            format!(
+                "could not create cache directory {}",
+                self.cache.dir.display()
+            )
+        })?;
+        for origin in &self.cors_origins {
+            if HeaderValue::from_str(origin).is_err() {
+                bail!("invalid CORS origin {origin:?}");
+            }
+        }
+        ensure!(
+            self.limits.max_code_bytes > 0,
+            "the maximum code size must be at least 1 byte"
+        );
+        ensure!(
+            self.limits.max_payload_bytes >= self.limits.max_code_bytes,
+            "the maximum payload size must be at least the maximum code size"
+        );
+        ensure!(
+            self.limits.max_files > 0,
+            "the maximum number of files must be at least 1"
+        );
+        for (name, bucket) in [
+            ("compile", self.rate_limit.compile),
+            ("paste", self.rate_limit.paste),
+        ] {
+            ensure!(
+                bucket.burst > 0 && bucket.per_minute > 0,
+                "the {name} rate limit must allow at least 1 request"
+            );
+        }
+        Ok(())
+    }
+
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout_secs)
+    }
+
+    pub fn compile_timeout(&self) -> Duration {
+        Duration::from_secs(self.compile_timeout_secs)
+    }
+
+    pub fn import_timeout(&self) -> Duration {
+        Duration::from_secs(self.import_timeout_secs)
+    }
+}
@@ -0,0 +1,72 @@
+//! Turning errors into API error responses.
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use playground_common::api::ApiError;
+
+use crate::gist::GistError;
+use crate::repo::ImportError;
+
+/// An [`ApiError`] that is sent to the client as a JSON response with the matching status code.
+#[derive(Debug)]
+pub struct ErrorResponse(pub ApiError);
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> Response {
+        let status =
+            StatusCode::from_u16(self.0.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (status, Json(self.0)).into_response()
+    }
+}
+
+impl From<ApiError> for ErrorResponse {
+    fn from(err: ApiError) -> Self {
+        Self(err)
+    }
+}
+
+impl From<anyhow::Error> for ErrorResponse {
+    fn from(err: anyhow::Error) -> Self {
+        Self(ApiError::Internal {
+            message: format!("{err:?}"),
+        })
+    }
+}
+
+impl From<GistError> for ErrorResponse {
+    fn from(err: GistError) -> Self {
+        Self(match err {
+            GistError::RateLimited => ApiError::RateLimited,
+            // The token of the server is at fault, not the client.
+            GistError::Unauthorized => ApiError::Unavailable {
+                message: "the server could not authenticate with GitHub".to_string(),
+            },
+            GistError::NotFound => ApiError::NotFound,
+            GistError::Upstream(message) => ApiError::Upstream { message },
+        })
+    }
+}
+
+impl From<ImportError> for ErrorResponse {
+    fn from(err: ImportError) -> Self {
+        match err {
+            ImportError::GitHub(err) => err.into(),
+            ImportError::InvalidProject(message) => Self(ApiError::InvalidInput { message }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn github_failures_are_not_blamed_on_the_client() {
+        let status = |err: GistError| ErrorResponse::from(err).0.status_code();
+        assert_eq!(status(GistError::Unauthorized), 503);
+        assert_eq!(status(GistError::Upstream(String::new())), 502);
+        assert_eq!(status(GistError::RateLimited), 429);
+        assert_eq!(status(GistError::NotFound), 404);
+    }
+}
@@ -0,0 +1,191 @@
+//! Storing and retrieving code snippets as GitHub gists.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use axum::http::{HeaderMap, StatusCode};
+use reqwest::Response;
+use serde::Deserialize;
+use serde_json::json;
+
+/// An error that occurred while talking to the GitHub gist API.
+#[derive(Debug)]
+pub enum GistError {
+    /// The GitHub API rate limit was exceeded.
+    RateLimited,
+    /// The GitHub token of the server is missing or was rejected by GitHub.
+    Unauthorized,
+    /// The requested gist does not exist or does not contain a `main.rs` file.
+    NotFound,
+    /// GitHub could not be reached or returned an unexpected response.
+    Upstream(String),
+}
+
+impl GistError {
+    /// Maps an error status code returned by GitHub into a `GistError`.
+    pub fn from_response(res: &Response) -> Self {
+        Self::from_status(res.status(), res.headers())
+    }
+
+    fn from_status(status: StatusCode, headers: &HeaderMap) -> Self {
+        // GitHub responds with 403 both to rejected tokens and to exceeded rate limits. The
+        // primary rate limit is exhausted when no requests remain, the secondary rate limits set
+        // `Retry-After`.
+        let rate_limit_exhausted = headers
+            .get("x-ratelimit-remaining")
+            .and_then(|remaining| remaining.to_str().ok())
+            == Some("0");
+        let retry_after = headers.contains_key("retry-after");
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited,
+            StatusCode::FORBIDDEN if rate_limit_exhausted || retry_after => Self::RateLimited,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::Unauthorized,
+            StatusCode::NOT_FOUND => Self::NotFound,
+            status => Self::Upstream(format!("GitHub API responded with {status}")),
+        }
+    }
+}
+
+impl fmt::Display for GistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RateLimited => write!(f, "GitHub API rate limit exceeded, try again later"),
+            Self::Unauthorized => write!(f, "could not authenticate with the GitHub API"),
+            Self::NotFound => write!(f, "gist not found"),
+            Self::Upstream(err) => write!(f, "GitHub API unavailable: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for GistError {}
+
+impl From<reqwest::Error> for GistError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Upstream(err.to_string())
+    }
+}
+
+/// Create a GitHub gist and return the id of the new gist.
+pub async fn create_gist(github_token: &str, code: &str) -> Result<String, GistError> {
+    #[derive(Deserialize)]
+    struct CreateGistRes {
+        id: String,
+    }
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post("https://api.github.com/gists")
+        .basic_auth("sycamore-playground", Some(github_token))
+        .header("User-Agent", "sycamore-playground")
+        .json(&json!({
+            "files": {
+                "main.rs": { "content": code }
+            },
+            "public": true
+        }))
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        return Err(GistError::from_response(&res));
+    }
+    let gist_id = res
+        .json::<CreateGistRes>()
+        .await
+        .map_err(|err| GistError::Upstream(format!("could not parse GitHub API response: {err}")))?
+        .id;
+    Ok(gist_id)
+}
+
+/// Fetch the content of the `main.rs` file in the gist with the given id.
+pub async fn fetch_gist(id: &str) -> Result<String, GistError> {
+    #[derive(Deserialize)]
+    struct GetGistRes {
+        files: HashMap<String, File>,
+    }
+    #[derive(Deserialize)]
+    struct File {
+        content: String,
+    }
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(format!("https://api.github.com/gists/{id}"))
+        .header("User-Agent", "sycamore-playground")
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        return Err(GistError::from_response(&res));
+    }
+    let content = res
+        .json::<GetGistRes>()
+        .await
+        .map_err(|err| GistError::Upstream(format!("could not parse GitHub API response: {err}")))?
+        .files
+        .remove("main.rs")
+        .ok_or(GistError::NotFound)?
+        .content;
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (*name, HeaderValue::from_static(value)))
+            .map(|(name, value)| (name.parse().unwrap(), value))
+            .collect()
+    }
+
+    #[test]
+    fn forbidden_with_exhausted_rate_limit_is_rate_limited() {
+        let headers = headers(&[("x-ratelimit-remaining", "0")]);
+        assert!(matches!(
+            GistError::from_status(StatusCode::FORBIDDEN, &headers),
+            GistError::RateLimited
+        ));
+    }
+
+    #[test]
+    fn forbidden_with_retry_after_is_rate_limited() {
+        let headers = headers(&[("x-ratelimit-remaining", "42"), ("retry-after", "60")]);
+        assert!(matches!(
+            GistError::from_status(StatusCode::FORBIDDEN, &headers),
+            GistError::RateLimited
+        ));
+    }
+
+    #[test]
+    fn forbidden_with_remaining_requests_is_unauthorized() {
+        let headers = headers(&[("x-ratelimit-remaining", "42")]);
+        assert!(matches!(
+            GistError::from_status(StatusCode::FORBIDDEN, &headers),
+            GistError::Unauthorized
+        ));
+        assert!(matches!(
+            GistError::from_status(StatusCode::UNAUTHORIZED, &HeaderMap::new()),
+            GistError::Unauthorized
+        ));
+    }
+
+    #[test]
+    fn other_statuses() {
+        let headers = HeaderMap::new();
+        assert!(matches!(
+            GistError::from_status(StatusCode::TOO_MANY_REQUESTS, &headers),
+            GistError::RateLimited
+        ));
+        assert!(matches!(
+            GistError::from_status(StatusCode::NOT_FOUND, &headers),
+            GistError::NotFound
+        ));
+        assert!(matches!(
+            GistError::from_status(StatusCode::BAD_GATEWAY, &headers),
+            GistError::Upstream(_)
+        ));
+    }
+}
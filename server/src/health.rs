@@ -0,0 +1,211 @@
+//! Liveness and readiness checks for orchestrators and load balancers.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::Extension;
+use axum::http::StatusCode;
+use axum::Json;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::fs;
+use tokio::process::Command;
+
+use crate::config::Config;
+use crate::warmup::{Warmup, WarmupState};
+
+/// How long a tool may take to print its version.
+const TOOL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the output of a tool is reused, so that frequent probes do not spawn it every time.
+const TOOL_OUTPUT_TTL: Duration = Duration::from_secs(30);
+
+/// The output of a tool and when it was run.
+type ToolOutput = Option<(Instant, Result<String, String>)>;
+
+/// The last output of every tool, by the command line. Each entry has its own lock, so that
+/// concurrent probes wait for the same run of the tool.
+static TOOL_OUTPUTS: Lazy<Mutex<HashMap<String, Arc<tokio::sync::Mutex<ToolOutput>>>>> =
+    Lazy::new(Default::default);
+
+/// The outcome of a single readiness check.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub ok: bool,
+    /// The version of the tool, or why the check failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl Check {
+    fn ok(detail: impl Into<Option<String>>) -> Self {
+        Self {
+            ok: true,
+            detail: detail.into(),
+        }
+    }
+
+    fn failed(detail: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            detail: Some(detail.into()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub checks: BTreeMap<&'static str, Check>,
+}
+
+/// `/healthz`: the server is running. Does not check anything else, so that a slow or broken
+/// toolchain does not get the process restarted.
+pub async fn get_healthz() -> Json<Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+/// `/readyz`: the server can compile code. Responds with `503 Service Unavailable` if any of the
+/// checks fail.
+pub async fn get_readyz(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(warmup): Extension<Arc<Warmup>>,
+) -> (StatusCode, Json<Readiness>) {
+    let readiness = check_readiness(&config, &warmup).await;
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness))
+}
+
+pub async fn check_readiness(config: &Config, warmup: &Warmup) -> Readiness {
+    let workspace = &config.workspace;
+    let (cargo, rustc, wasm32_target, cache_dir) = tokio::join!(
+        tool_version(workspace, "cargo"),
+        tool_version(workspace, "rustc"),
+        check_wasm32_target(workspace),
+        check_cache_dir(&config.cache.dir),
+    );
+
+    let mut checks = BTreeMap::new();
+    checks.insert("cargo", cargo);
+    checks.insert("rustc", rustc);
+    checks.insert("wasm32_target", wasm32_target);
+    checks.insert("workspace", check_workspace(workspace));
+    checks.insert("workspace_warmed", check_warmed(warmup));
+    checks.insert("cache_dir", cache_dir);
+    Readiness {
+        ready: checks.values().all(|check| check.ok),
+        checks,
+    }
+}
+
+/// Runs the command with the given arguments in the workspace and returns its trimmed stdout.
+/// Reuses the output of the last run for [`TOOL_OUTPUT_TTL`].
+async fn run_tool(workspace: &Path, tool: &str, args: &[&str]) -> Result<String, String> {
+    let entry = TOOL_OUTPUTS
+        .lock()
+        .unwrap()
+        .entry(format!("{tool} {}", args.join(" ")))
+        .or_default()
+        .clone();
+    let mut entry = entry.lock().await;
+    if let Some((ran_at, output)) = &*entry {
+        if ran_at.elapsed() < TOOL_OUTPUT_TTL {
+            return output.clone();
+        }
+    }
+    let output = run_tool_uncached(workspace, tool, args).await;
+    *entry = Some((Instant::now(), output.clone()));
+    output
+}
+
+async fn run_tool_uncached(workspace: &Path, tool: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(tool)
+        .args(args)
+        .current_dir(workspace)
+        .kill_on_drop(true)
+        .output();
+    match tokio::time::timeout(TOOL_TIMEOUT, output).await {
+        Ok(Ok(output)) if output.status.success() => {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        }
+        Ok(Ok(output)) => Err(format!(
+            "`{tool}` exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Ok(Err(err)) => Err(format!("could not run `{tool}`: {err}")),
+        Err(_) => Err(format!("`{tool}` did not respond")),
+    }
+}
+
+async fn tool_version(workspace: &Path, tool: &str) -> Check {
+    match run_tool(workspace, tool, &["--version"]).await {
+        Ok(version) => Check::ok(version),
+        Err(err) => Check::failed(err),
+    }
+}
+
+/// Checks that the standard library for `wasm32-unknown-unknown` is installed in the sysroot of
+/// the toolchain that the workspace uses.
+async fn check_wasm32_target(workspace: &Path) -> Check {
+    let sysroot = match run_tool(workspace, "rustc", &["--print", "sysroot"]).await {
+        Ok(sysroot) => sysroot,
+        Err(err) => return Check::failed(err),
+    };
+    let target_dir = Path::new(&sysroot).join("lib/rustlib/wasm32-unknown-unknown");
+    if fs::metadata(&target_dir).await.is_ok() {
+        Check::ok(None)
+    } else {
+        Check::failed(
+            "the wasm32-unknown-unknown target is not installed. Run `rustup target add \
+             wasm32-unknown-unknown`",
+        )
+    }
+}
+
+fn check_workspace(workspace: &Path) -> Check {
+    let manifest = workspace.join("Cargo.toml");
+    if manifest.is_file() {
+        Check::ok(None)
+    } else {
+        Check::failed(format!("{} is missing", manifest.display()))
+    }
+}
+
+fn check_warmed(warmup: &Warmup) -> Check {
+    match warmup.state() {
+        WarmupState::Ready => Check::ok(None),
+        WarmupState::Warming => Check::failed("the workspace is being warmed up"),
+        WarmupState::Failed(err) => {
+            Check::failed(format!("warming up the workspace failed: {err}"))
+        }
+    }
+}
+
+async fn check_cache_dir(dir: &Path) -> Check {
+    static PROBE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    // Concurrent probes, also of other servers sharing the directory, each write their own file.
+    let probe = dir.join(format!(
+        ".readyz-{}-{}",
+        std::process::id(),
+        PROBE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let res = async {
+        fs::write(&probe, b"ok").await?;
+        fs::remove_file(&probe).await
+    }
+    .await;
+    match res {
+        Ok(()) => Check::ok(None),
+        Err(err) => Check::failed(format!("{} is not writable: {err}", dir.display())),
+    }
+}
@@ -0,0 +1,285 @@
+//! Compile jobs.
+//!
+//! Every compile runs as a job in the background with its own deadline, independent of the HTTP
+//! request that started it. Clients whose request times out or who disconnect can poll the job
+//! for its result instead of starting a new build, or cancel it to free the workspace.
+
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use playground_common::api::{CompileRequest, JobStatus};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, info_span, warn, Instrument};
+
+use crate::compile::{needs_warm_up, process_compile};
+use crate::config::Config;
+use crate::metrics::METRICS;
+use crate::warmup::Warmup;
+
+/// How long the result of a finished job is kept around for clients to fetch.
+const JOB_RETENTION: Duration = Duration::from_secs(10 * 60);
+
+/// How many finished jobs are kept at most. Their results are kept in memory, so older ones are
+/// removed before [`JOB_RETENTION`] is up if more jobs finish.
+const MAX_FINISHED_JOBS: usize = 50;
+
+#[derive(Debug, Clone)]
+pub enum JobState {
+    /// The job is waiting for the workspace.
+    Queued,
+    /// cargo is compiling the code.
+    Building,
+    /// wasm-bindgen is generating the JS glue code for the wasm binary.
+    Bundling,
+    /// The job finished. Contains the `CompileResponse` serialized with
+    /// [`CACHE_ENCODING`](crate::compile::CACHE_ENCODING).
+    Done(Arc<Vec<u8>>),
+    /// The job failed because of an error on the server.
+    Failed(String),
+    /// The job took longer than the compile timeout and was killed.
+    TimedOut,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, Self::Queued | Self::Building | Self::Bundling)
+    }
+
+    pub fn status(&self) -> JobStatus {
+        match self {
+            Self::Queued => JobStatus::Queued,
+            Self::Building => JobStatus::Building,
+            Self::Bundling => JobStatus::Bundling,
+            Self::Done(_) => JobStatus::Done,
+            Self::Failed(message) => JobStatus::Failed {
+                message: message.clone(),
+            },
+            Self::TimedOut => JobStatus::TimedOut,
+            Self::Cancelled => JobStatus::Cancelled,
+        }
+    }
+}
+
+pub struct Job {
+    id: String,
+    state: watch::Sender<JobState>,
+    cancel: CancellationToken,
+}
+
+impl Job {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn state(&self) -> JobState {
+        self.state.borrow().clone()
+    }
+
+    /// Reports the progress of the job. Called by the job runner.
+    pub fn set_state(&self, state: JobState) {
+        self.state.send_replace(state);
+    }
+
+    /// Cancels the job, killing the build if it is running. Has no effect if the job has already
+    /// finished.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Waits for the job to finish and returns its final state.
+    pub async fn wait(&self) -> JobState {
+        let mut state = self.state.subscribe();
+        loop {
+            if state.borrow().is_finished() {
+                return state.borrow().clone();
+            }
+            if state.changed().await.is_err() {
+                return self.state();
+            }
+        }
+    }
+}
+
+/// The jobs that are running or have finished recently.
+pub struct Jobs {
+    jobs: Mutex<JobMap>,
+    warmup: Arc<Warmup>,
+}
+
+#[derive(Default)]
+struct JobMap {
+    jobs: HashMap<String, Arc<Job>>,
+    /// The ids of the finished jobs, oldest first.
+    finished: VecDeque<String>,
+}
+
+impl JobMap {
+    /// Records that a job has finished, removing the oldest finished jobs if there are more than
+    /// [`MAX_FINISHED_JOBS`].
+    fn finish(&mut self, id: &str) {
+        if !self.jobs.contains_key(id) {
+            return;
+        }
+        self.finished.push_back(id.to_string());
+        while self.finished.len() > MAX_FINISHED_JOBS {
+            let oldest = self.finished.pop_front().expect("not empty");
+            self.jobs.remove(&oldest);
+        }
+    }
+
+    fn remove(&mut self, id: &str) {
+        if self.jobs.remove(id).is_some() {
+            self.finished.retain(|finished| finished != id);
+        }
+    }
+}
+
+impl Jobs {
+    pub fn new(warmup: Arc<Warmup>) -> Self {
+        Self {
+            jobs: Mutex::default(),
+            warmup,
+        }
+    }
+
+    /// Starts a new compile job. Returns the id of the job and the job itself.
+    pub fn spawn(
+        self: &Arc<Self>,
+        config: Arc<Config>,
+        req: CompileRequest<'static>,
+    ) -> (String, Arc<Job>) {
+        let id = new_job_id();
+        let job = Arc::new(Job {
+            id: id.clone(),
+            state: watch::channel(JobState::Queued).0,
+            cancel: CancellationToken::new(),
+        });
+        self.jobs
+            .lock()
+            .unwrap()
+            .jobs
+            .insert(id.clone(), job.clone());
+
+        let jobs = self.clone();
+        let job_id = id.clone();
+        let runner = job.clone();
+        // The span is a child of the span of the request that started the job.
+        let span = info_span!("compile_job", job_id = %id);
+        let task = async move {
+            let compile = process_compile(&config, req, &runner);
+            // The deadline starts once the job has the workspace, waiting for other builds does
+            // not count. Cache hits finish without leaving the queue.
+            let deadline = async {
+                let mut state = runner.state.subscribe();
+                while matches!(*state.borrow_and_update(), JobState::Queued) {
+                    if state.changed().await.is_err() {
+                        break;
+                    }
+                }
+                tokio::time::sleep(config.compile_timeout()).await;
+            };
+            // Dropping the compile future when the job is cancelled or the deadline is reached
+            // kills the build.
+            let state = tokio::select! {
+                res = compile => match res {
+                    Ok(bytes) => JobState::Done(Arc::new(bytes)),
+                    Err(err) => {
+                        error!(error = ?err, "compile job failed");
+                        if needs_warm_up(&err) {
+                            jobs.warmup.restart(config.clone());
+                        }
+                        JobState::Failed(format!("{err:?}"))
+                    }
+                },
+                _ = deadline => {
+                    warn!(timeout_secs = config.compile_timeout_secs, "compile job timed out");
+                    JobState::TimedOut
+                }
+                _ = runner.cancel.cancelled() => {
+                    info!("compile job cancelled");
+                    JobState::Cancelled
+                }
+            };
+            let outcome = match state {
+                JobState::Failed(_) => Some("failed"),
+                JobState::TimedOut => Some("timed_out"),
+                JobState::Cancelled => Some("cancelled"),
+                // Successful compiles and compile errors are counted by `process_compile`.
+                _ => None,
+            };
+            if let Some(outcome) = outcome {
+                METRICS.compiles.with_label_values(&[outcome]).inc();
+            }
+            runner.set_state(state);
+            // The result must not outlive the job in the map.
+            drop(runner);
+            jobs.jobs.lock().unwrap().finish(&job_id);
+
+            tokio::time::sleep(JOB_RETENTION).await;
+            jobs.remove(&job_id);
+        };
+        tokio::spawn(task.instrument(span));
+
+        (id, job)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<Job>> {
+        self.jobs.lock().unwrap().jobs.get(id).cloned()
+    }
+
+    /// Removes a job, e.g. once its result has been sent to the client.
+    pub fn remove(&self, id: &str) {
+        self.jobs.lock().unwrap().remove(id);
+    }
+}
+
+/// Generates a new, hard to guess job id.
+fn new_job_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let mut hasher = RandomState::new().build_hasher();
+    COUNTER.fetch_add(1, Ordering::Relaxed).hash(&mut hasher);
+    SystemTime::now().hash(&mut hasher);
+    base64::encode_config(hasher.finish().to_le_bytes(), base64::URL_SAFE_NO_PAD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(map: &mut JobMap, id: &str) {
+        let job = Arc::new(Job {
+            id: id.to_string(),
+            state: watch::channel(JobState::Queued).0,
+            cancel: CancellationToken::new(),
+        });
+        map.jobs.insert(id.to_string(), job);
+    }
+
+    #[test]
+    fn oldest_finished_jobs_are_removed() {
+        let mut map = JobMap::default();
+        insert(&mut map, "running");
+        for i in 0..=MAX_FINISHED_JOBS {
+            let id = i.to_string();
+            insert(&mut map, &id);
+            map.finish(&id);
+        }
+        assert!(!map.jobs.contains_key("0"));
+        assert!(map.jobs.contains_key("1"));
+        assert!(map.jobs.contains_key("running"));
+        assert_eq!(map.finished.len(), MAX_FINISHED_JOBS);
+
+        map.remove("1");
+        assert_eq!(map.finished.len(), MAX_FINISHED_JOBS - 1);
+        // Jobs that were removed before they finished are not counted.
+        map.finish("1");
+        assert_eq!(map.finished.len(), MAX_FINISHED_JOBS - 1);
+    }
+}
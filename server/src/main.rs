@@ -1,230 +1,487 @@
-use std::collections::HashMap;
-use std::collections::{hash_map::DefaultHasher, HashSet};
-use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
-use std::time::Duration;
+mod artifact;
+mod assets;
+mod bundle;
+mod compile;
+mod config;
+mod error;
+mod gist;
+mod health;
+mod jobs;
+mod metrics;
+mod rate_limit;
+mod repo;
+mod testing;
+mod validate;
+mod warmup;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
 
 use anyhow::{Context, Result};
 use axum::error_handling::HandleErrorLayer;
-use axum::extract::{Form, Path};
-use axum::handler::Handler;
-use axum::http::{Method, StatusCode};
+use axum::extract::{Extension, Path};
+use axum::http::header::HeaderName;
+use axum::http::{HeaderMap, HeaderValue, Method, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::{http, BoxError, Json, Router};
-use once_cell::sync::Lazy;
-use playground_common::{CompileRequest, CompileResponse, PasteRequest};
-use serde::Deserialize;
-use serde_json::json;
-use tokio::{fs, process::Command, sync::Mutex};
+use playground_common::api::{
+    self, ApiError, CompileJob, CompileRequest, CompileResponse, CreatePasteRequest,
+    CreatePasteResponse, GetPasteResponse, ImportedProject, JobStatus, TestRequest, TestResponse,
+};
+use playground_common::encoding::Encoding;
 use tower::ServiceBuilder;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tracing::{error, info, info_span, warn, Level, Span};
+use tracing_subscriber::EnvFilter;
 
-const CACHE_DIR: &str = "cache";
+use crate::compile::CACHE_ENCODING;
+use crate::config::{Config, LogFormat, PasteBackend};
+use crate::error::ErrorResponse;
+use crate::gist::{create_gist, fetch_gist};
+use crate::jobs::{JobState, Jobs};
+use crate::metrics::METRICS;
+use crate::rate_limit::{ClientPolicy, RateLimitLayer};
+use crate::repo::RepoPath;
+use crate::validate::{ValidJson, ZipBody};
+use crate::warmup::Warmup;
 
 async fn get_index() -> &'static str {
     "Sycamore playground compiler service. Source code: https://github.com/sycamore-rs/playground"
 }
 
-fn hash_str(s: &str) -> String {
-    let mut hasher = DefaultHasher::new();
-    s.hash(&mut hasher);
-    let hash = hasher.finish();
-    base64::encode_config(hash.to_le_bytes(), base64::URL_SAFE)
+/// Picks the encoding of the response from the `Accept` header.
+fn response_encoding(headers: &HeaderMap) -> Result<Encoding, ErrorResponse> {
+    let accept = headers
+        .get(http::header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok());
+    Ok(Encoding::from_accept(accept).ok_or(ApiError::NotAcceptable)?)
 }
 
-/// Compile the code and store the result in a cache. Returns a serialized version of `CompileResponse`.
-/// If the code has already been compiled and is found in the cache, returns the cached binary instead of recompiling.
-async fn process_compile(CompileRequest { code }: CompileRequest<'_>) -> Result<Vec<u8>> {
-    static LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
-    static CACHE: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+/// Turns the state of a finished job into a response with the `CompileResponse` serialized with
+/// the given encoding.
+fn job_result_response(state: JobState, encoding: Encoding) -> Result<Response, ErrorResponse> {
+    let bytes = match state {
+        JobState::Done(bytes) => bytes,
+        JobState::Queued | JobState::Building | JobState::Bundling => {
+            return Err(ApiError::JobNotFinished.into())
+        }
+        JobState::Failed(message) => return Err(ApiError::Internal { message }.into()),
+        JobState::TimedOut => return Err(ApiError::Timeout.into()),
+        JobState::Cancelled => return Err(ApiError::JobCancelled.into()),
+    };
+    let body = if encoding == CACHE_ENCODING {
+        bytes.to_vec()
+    } else {
+        let res: CompileResponse = CACHE_ENCODING.decode(&bytes).map_err(anyhow::Error::from)?;
+        encoding.encode(&res).map_err(anyhow::Error::from)?
+    };
+    let content_type = HeaderValue::from_static(encoding.mime_type());
+    Ok(([(http::header::CONTENT_TYPE, content_type)], body).into_response())
+}
 
-    let code_hash = hash_str(&code);
-    let cache_file_name: PathBuf = [CACHE_DIR, &format!("{code_hash}.bin")].iter().collect();
-    // First check if we have a cached version.
-    if CACHE.lock().await.contains(&code_hash) {
-        // Deserialize the cached file into a `CompileResponse`.
-        let res = fs::read(cache_file_name).await?;
-        // Return the cached file.
-        return Ok(res);
+/// A `202 Accepted` response pointing the client to the job.
+fn job_accepted_response(id: String) -> Result<Response, ErrorResponse> {
+    let location = HeaderValue::try_from(api::job_path(&id)).map_err(anyhow::Error::from)?;
+    Ok((
+        StatusCode::ACCEPTED,
+        [(http::header::LOCATION, location)],
+        Json(CompileJob { id }),
+    )
+        .into_response())
+}
+
+/// Starts a compile job and waits for it until the request timeout. Compiles that take longer keep
+/// running in the background and the client is sent the id of the job to poll instead.
+async fn handle_compile(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(jobs): Extension<Arc<Jobs>>,
+    headers: HeaderMap,
+    ValidJson(payload): ValidJson<CompileRequest<'static>>,
+) -> Result<Response, ErrorResponse> {
+    let encoding = response_encoding(&headers)?;
+    let (id, job) = jobs.spawn(config.clone(), payload);
+    match tokio::time::timeout(config.request_timeout(), job.wait()).await {
+        Ok(state) => {
+            // The client gets the result right away and never polls for it.
+            jobs.remove(&id);
+            job_result_response(state, encoding)
+        }
+        Err(_) => job_accepted_response(id),
     }
+}
 
-    // Acquire the lock to prevent multiple requests from compiling at the same time.
-    let _guard = LOCK.lock().await;
+async fn post_job(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(jobs): Extension<Arc<Jobs>>,
+    ValidJson(payload): ValidJson<CompileRequest<'static>>,
+) -> Result<Response, ErrorResponse> {
+    let (id, _) = jobs.spawn(config, payload);
+    job_accepted_response(id)
+}
 
-    fs::write("../playground/src/main.rs", code.as_bytes()).await?;
+async fn get_job(
+    Extension(jobs): Extension<Arc<Jobs>>,
+    Path(job_id): Path<String>,
+) -> Result<Json<JobStatus>, ErrorResponse> {
+    let job = jobs.get(&job_id).ok_or(ApiError::NotFound)?;
+    Ok(Json(job.state().status()))
+}
 
-    let cargo_build = Command::new("cargo")
-        .env_remove("GITHUB_TOKEN")
-        .arg("build")
-        .arg("--target")
-        .arg("wasm32-unknown-unknown")
-        .current_dir("../playground")
-        .output()
-        .await?;
+/// Cancels the job and waits for the build to be killed, so that the workspace is free once the
+/// response is sent.
+async fn delete_job(
+    Extension(jobs): Extension<Arc<Jobs>>,
+    Path(job_id): Path<String>,
+) -> Result<StatusCode, ErrorResponse> {
+    let job = jobs.get(&job_id).ok_or(ApiError::NotFound)?;
+    job.cancel();
+    job.wait().await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_job_artifact(
+    Extension(jobs): Extension<Arc<Jobs>>,
+    Path(job_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, ErrorResponse> {
+    let encoding = response_encoding(&headers)?;
+    let job = jobs.get(&job_id).ok_or(ApiError::NotFound)?;
+    let state = job.state();
+    // The result is only kept until it has been fetched.
+    if let JobState::Done(_) = state {
+        jobs.remove(&job_id);
+    }
+    job_result_response(state, encoding)
+}
 
-    if cargo_build.status.success() {
-        // Call trunk to orchestrate wasm-bindgen and js glue code generation.
-        let _output = Command::new("trunk")
-            .env_remove("GITHUB_TOKEN")
-            .args(["build", "../playground/index.html", "--filehash", "false"])
-            .output()
-            .await
-            .context("call trunk")?;
-
-        // Read the generated artifacts and serialize them into a `CompileResponse`.
-        let wasm = fs::read("../playground/dist/playground_bg.wasm")
-            .await
-            .context("Could not read wasm artifact.")?;
-        let js = fs::read_to_string("../playground/dist/playground.js")
-            .await
-            .context("Could not read js artifact.")?;
-        let res = CompileResponse::Success {
-            wasm: wasm.into(),
-            js: js.into(),
-        };
-        let bytes = bincode::serialize(&res).context("Could not serialize result with bincode.")?;
-
-        // Add the generated file to the cache.
-        CACHE.lock().await.insert(code_hash);
-        fs::create_dir_all(CACHE_DIR).await?;
-        fs::write(cache_file_name, &bytes)
-            .await
-            .context("Could not write cache file.")?;
-
-        Ok(bytes)
+async fn handle_timeout_error(err: BoxError) -> ErrorResponse {
+    if err.is::<tower::timeout::error::Elapsed>() {
+        ApiError::Timeout.into()
     } else {
-        // Compile error. We don't want to return `Err(_)` because we want to serialize the error into a `CompileResponse`.
-        let res =
-            CompileResponse::CompileError(String::from_utf8_lossy(&cargo_build.stderr).to_string());
-        let bytes = bincode::serialize(&res)?;
-        Ok(bytes)
+        ApiError::Internal {
+            message: format!("{err}"),
+        }
+        .into()
     }
 }
 
-async fn handle_compile(Json(payload): Json<CompileRequest<'_>>) -> (StatusCode, Vec<u8>) {
-    match process_compile(payload).await {
-        Ok(bytes) => (StatusCode::OK, bytes),
+async fn get_metrics() -> ([(HeaderName, HeaderValue); 1], String) {
+    let content_type = HeaderValue::from_static("text/plain; version=0.0.4");
+    (
+        [(http::header::CONTENT_TYPE, content_type)],
+        METRICS.render(),
+    )
+}
+
+async fn post_gist(
+    Extension(config): Extension<Arc<Config>>,
+    ValidJson(payload): ValidJson<CreatePasteRequest<'static>>,
+) -> Result<Json<CreatePasteResponse>, ErrorResponse> {
+    let token = github_token(&config)?;
+    let res = create_gist(token, &payload.code).await;
+    METRICS.record_paste("create", &res);
+    match res {
+        Ok(id) => Ok(Json(CreatePasteResponse { id })),
         Err(err) => {
-            eprintln!("{err:?}");
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("{err:?}").into_bytes(),
-            )
+            error!(error = %err, "could not create gist");
+            Err(err.into())
         }
     }
 }
 
-async fn handle_timeout_error(err: BoxError) -> (StatusCode, String) {
-    if err.is::<tower::timeout::error::Elapsed>() {
-        (StatusCode::REQUEST_TIMEOUT, "Request timed out".to_string())
-    } else {
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("{err}"))
+async fn get_gist(
+    Extension(config): Extension<Arc<Config>>,
+    Path(paste_id): Path<String>,
+) -> Result<Json<GetPasteResponse<'static>>, ErrorResponse> {
+    // Gists are public, reading them does not need the token.
+    check_sharing_enabled(&config)?;
+    let res = fetch_gist(&paste_id).await;
+    METRICS.record_paste("fetch", &res);
+    match res {
+        Ok(code) => Ok(Json(GetPasteResponse { code: code.into() })),
+        Err(err) => {
+            warn!(error = %err, gist_id = %paste_id, "could not fetch gist");
+            Err(err.into())
+        }
     }
 }
 
-/// Create a GitHub gist and return the id of the new gist.
-async fn create_gist(code: &str) -> Result<String> {
-    #[derive(Deserialize)]
-    struct CreateGistRes {
-        id: String,
+async fn get_repo(
+    Extension(config): Extension<Arc<Config>>,
+    Path(repo): Path<String>,
+) -> Result<Json<ImportedProject<'static>>, ErrorResponse> {
+    let repo = RepoPath::parse(&repo).ok_or_else(|| ApiError::BadRequest {
+        message: "expected a repository as `owner/name`, optionally followed by a path".to_string(),
+    })?;
+    let allowed_crates = repo::allowed_crates(&config.workspace).await?;
+    // The token is not required, but raises the rate limit of the GitHub API.
+    let github_token = config.paste.github_token.as_deref();
+    let res = repo::import(&repo, github_token, &config.limits, &allowed_crates).await;
+    METRICS.record_import(&res);
+    match res {
+        Ok(project) => Ok(Json(project)),
+        Err(err) => {
+            warn!(error = %err, %repo, "could not import repository");
+            Err(err.into())
+        }
     }
+}
 
-    let client = reqwest::Client::new();
-    let github_token = std::env::var("GITHUB_TOKEN").context("Could not get GITHUB_TOKEN")?;
-    let res = client
-        .post("https://api.github.com/gists")
-        .basic_auth("sycamore-playground", Some(github_token))
-        .header("User-Agent", "sycamore-playground")
-        .json(&json!({
-            "files": {
-                "main.rs": { "content": code }
-            },
-            "public": true
-        }))
-        .send()
-        .await
-        .context("sending HTTP request")?;
-    let res_text = res.text().await?;
-    let gist_id = serde_json::from_str::<CreateGistRes>(&res_text)
-        .context("could not parse github API response")?
-        .id;
-    Ok(gist_id)
-}
-
-async fn fetch_gist(id: &str) -> Result<String> {
-    #[derive(Deserialize)]
-    struct GetGistRes {
-        files: HashMap<String, File>,
-    }
-    #[derive(Deserialize)]
-    struct File {
-        content: String,
+async fn post_import(
+    Extension(config): Extension<Arc<Config>>,
+    ZipBody(zip): ZipBody,
+) -> Result<Json<ImportedProject<'static>>, ErrorResponse> {
+    let allowed_crates = repo::allowed_crates(&config.workspace).await?;
+    let res = repo::import_zip(zip, &config.limits, &allowed_crates).await;
+    METRICS.record_import(&res);
+    match res {
+        Ok(project) => Ok(Json(project)),
+        Err(err) => {
+            warn!(error = %err, "could not import archive");
+            Err(err.into())
+        }
     }
+}
 
-    let client = reqwest::Client::new();
-    let res = client
-        .get(&format!("https://api.github.com/gists/{id}"))
-        .header("User-Agent", "sycamore-playground")
-        .send()
-        .await?;
-    let res_text = res.text().await?;
-    let content = serde_json::from_str::<GetGistRes>(&res_text)
-        .context("could not parse github API response")?
-        .files
-        .get("main.rs")
-        .context("missing file main.rs from gist")?
-        .content
-        .clone();
-    Ok(content)
-}
-
-async fn post_gist(Form(form): Form<PasteRequest<'_>>) -> (StatusCode, String) {
-    match create_gist(&form.code).await {
-        Ok(paste_url) => (StatusCode::OK, paste_url),
-        Err(err) => {
-            eprintln!("{err:?}");
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("{err:?}"))
+/// Builds and runs the tests in the code. Unlike compiles, test runs are not cached and do not run
+/// as jobs, since their results may change with every run.
+async fn handle_test(
+    Extension(config): Extension<Arc<Config>>,
+    ValidJson(payload): ValidJson<TestRequest<'static>>,
+) -> Result<Json<TestResponse>, ErrorResponse> {
+    if !config.enable_tests {
+        return Err(ApiError::Unavailable {
+            message: "running tests is disabled on this server".to_string(),
         }
+        .into());
+    }
+    let res = tokio::time::timeout(
+        config.compile_timeout(),
+        testing::run_tests(&config, &payload.code),
+    )
+    .await;
+    let outcome = match &res {
+        Ok(Ok(TestResponse::Finished { .. })) => "finished",
+        Ok(Ok(TestResponse::CompileError(_))) => "compile_error",
+        Ok(Ok(TestResponse::RunError(_))) => "run_error",
+        Ok(Err(_)) => "failed",
+        Err(_) => "timed_out",
+    };
+    METRICS.test_runs.with_label_values(&[outcome]).inc();
+    match res {
+        Ok(Ok(res)) => Ok(Json(res)),
+        Ok(Err(err)) => {
+            error!(error = ?err, "could not run tests");
+            Err(err.into())
+        }
+        Err(_) => Err(ApiError::Timeout.into()),
     }
 }
 
-async fn get_gist(Path(paste_id): Path<String>) -> (StatusCode, String) {
-    match fetch_gist(&paste_id).await {
-        Ok(paste_url) => (StatusCode::OK, paste_url),
-        Err(err) => {
-            eprintln!("{err:?}");
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("{err:?}"))
+/// Returns an error if sharing is disabled.
+fn check_sharing_enabled(config: &Config) -> Result<(), ErrorResponse> {
+    match config.paste.backend {
+        PasteBackend::Github => Ok(()),
+        PasteBackend::Disabled => Err(ApiError::Unavailable {
+            message: "sharing is disabled on this server".to_string(),
         }
+        .into()),
     }
 }
 
-#[tokio::main]
-async fn main() {
+/// Returns the GitHub token for creating gists, or an error if creating shares is disabled.
+fn github_token(config: &Config) -> Result<&str, ErrorResponse> {
+    check_sharing_enabled(config)?;
+    config.paste.github_token.as_deref().ok_or_else(|| {
+        ApiError::Unavailable {
+            message: "creating shares is disabled on this server because it has no GitHub token"
+                .to_string(),
+        }
+        .into()
+    })
+}
+
+/// Rejects requests made against a different version of the API and adds the version of the
+/// server to every response. Requests without a version header (e.g. from curl) are let through.
+async fn check_api_version<B>(req: Request<B>, next: Next<B>) -> Response {
+    let client_version = req
+        .headers()
+        .get(api::VERSION_HEADER)
+        .map(|version| version.to_str().ok().and_then(|v| v.parse::<u32>().ok()));
+    let mut res = match client_version {
+        Some(Some(client)) if client != api::VERSION => ErrorResponse(ApiError::VersionMismatch {
+            client,
+            server: api::VERSION,
+        })
+        .into_response(),
+        Some(None) => ErrorResponse(ApiError::BadRequest {
+            message: format!("invalid {} header", api::VERSION_HEADER),
+        })
+        .into_response(),
+        _ => next.run(req).await,
+    };
+    res.headers_mut()
+        .insert(api::VERSION_HEADER, HeaderValue::from(api::VERSION));
+    res
+}
+
+fn cors_layer(config: &Config) -> CorsLayer {
+    let allow_origin = if config.cors_origins.is_empty() {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            config
+                .cors_origins
+                .iter()
+                .map(|origin| HeaderValue::from_str(origin).expect("origins are validated")),
+        )
+    };
+    CorsLayer::new()
+        .allow_headers(vec![
+            http::header::CONTENT_TYPE,
+            HeaderName::from_static(api::VERSION_HEADER),
+            HeaderName::from_static(api::API_KEY_HEADER),
+        ])
+        .expose_headers(vec![
+            HeaderName::from_static(api::VERSION_HEADER),
+            http::header::LOCATION,
+            http::header::RETRY_AFTER,
+            HeaderName::from_static(api::REQUEST_ID_HEADER),
+        ])
+        .allow_methods(vec![
+            Method::GET,
+            Method::POST,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_origin(allow_origin)
+}
+
+/// The span that everything logged while handling a request belongs to.
+fn request_span<B>(req: &Request<B>) -> Span {
+    let request_id = req
+        .headers()
+        .get(api::REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+    info_span!(
+        "request",
+        method = %req.method(),
+        uri = %req.uri(),
+        request_id,
+    )
+}
+
+async fn serve(config: Config) -> Result<()> {
+    let config = Arc::new(config);
+    if config.paste.backend == PasteBackend::Github && config.paste.github_token.is_none() {
+        warn!("GITHUB_TOKEN is not set, creating shares is disabled");
+    }
+    if config.enable_tests {
+        info!(
+            uid = config.sandbox.uid,
+            "running tests is enabled, submitted code runs in the sandbox"
+        );
+    }
+    compile::remove_build_dirs(&config)
+        .await
+        .context("could not remove old build directories")?;
+    compile::load_cache(&config)
+        .await
+        .context("could not load the cache")?;
+    let warmup = Warmup::start(config.clone());
+    let jobs = Arc::new(Jobs::new(warmup.clone()));
+    let policy = Arc::new(ClientPolicy::new(&config.rate_limit));
+    let request_id_header = HeaderName::from_static(api::REQUEST_ID_HEADER);
+    // Compiles and test runs are bounded by their own timeouts, see `handle_compile` and
+    // `handle_test`.
+    let compile = Router::new()
+        .route(api::COMPILE_PATH, post(handle_compile))
+        .route(api::JOBS_PATH, post(post_job))
+        .route(api::TEST_PATH, post(handle_test))
+        .layer(RateLimitLayer::new(
+            &config.rate_limit,
+            config.rate_limit.compile,
+            policy.clone(),
+        ));
+    // Imports share the rate limit of pastes, since both call the GitHub API.
+    let paste_limit = RateLimitLayer::new(&config.rate_limit, config.rate_limit.paste, policy);
+    let paste = Router::new()
+        .route(api::PASTE_PATH, post(post_gist))
+        .route(&api::get_paste_path(":paste_id"), get(get_gist))
+        .layer(
+            ServiceBuilder::new()
+                .layer(paste_limit.clone())
+                .layer(HandleErrorLayer::new(handle_timeout_error))
+                .timeout(config.request_timeout()),
+        );
+    let import = Router::new()
+        .route(&api::repo_path("*repo"), get(get_repo))
+        .route(api::IMPORT_PATH, post(post_import))
+        .layer(
+            ServiceBuilder::new()
+                .layer(paste_limit)
+                .layer(HandleErrorLayer::new(handle_timeout_error))
+                .timeout(config.import_timeout()),
+        );
     let app = Router::new()
         .route("/", get(get_index))
+        .route("/healthz", get(health::get_healthz))
+        .route("/readyz", get(health::get_readyz))
+        .route("/metrics", get(get_metrics))
+        .merge(compile)
+        .route(&api::job_path(":job_id"), get(get_job).delete(delete_job))
+        .route(&api::job_artifact_path(":job_id"), get(get_job_artifact))
         .route(
-            "/compile",
-            post(
-                handle_compile.layer(
-                    ServiceBuilder::new()
-                        .layer(HandleErrorLayer::new(handle_timeout_error))
-                        .timeout(Duration::from_secs(4)),
-                ),
-            ),
+            &api::asset_path(":code_hash", "*path"),
+            get(assets::get_asset),
         )
-        .route("/paste", post(post_gist))
-        .route("/paste/:paste_id", get(get_gist))
+        .route(&api::bundle_path(":code_hash"), get(bundle::get_bundle))
+        .merge(paste)
+        .merge(import)
+        .layer(middleware::from_fn(check_api_version))
+        .layer(Extension(config.clone()))
+        .layer(Extension(jobs))
+        .layer(Extension(warmup))
+        .layer(cors_layer(&config))
+        .layer(PropagateRequestIdLayer::new(request_id_header.clone()))
         .layer(
-            CorsLayer::new()
-                .allow_headers(vec![http::header::CONTENT_TYPE])
-                .allow_methods(vec![Method::GET, Method::POST, Method::OPTIONS])
-                .allow_origin(Any),
-        );
+            TraceLayer::new_for_http()
+                .make_span_with(request_span)
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        // Keeps the id of requests that already have one, e.g. from a reverse proxy.
+        .layer(SetRequestIdLayer::new(request_id_header, MakeRequestUuid));
 
-    // Run on localhost:PORT.
-    let port = std::env::var("PORT").unwrap_or_else(|_| "3000".to_string());
-    axum::Server::bind(&format!("0.0.0.0:{port}").parse().unwrap())
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+    info!(addr = %config.bind, "listening");
+    axum::Server::bind(&config.bind)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await?;
+    Ok(())
+}
+
+fn init_tracing(format: LogFormat) {
+    // walrus, used by wasm-bindgen, warns about harmless quirks in every binary built by rustc.
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info,walrus=error"));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    match format {
+        LogFormat::Pretty => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+}
+
+fn main() -> Result<()> {
+    let config = Config::load()?;
+    init_tracing(config.log_format);
+
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    if let Some(workers) = config.workers {
+        runtime.worker_threads(workers);
+    }
+    runtime.enable_all().build()?.block_on(serve(config))
 }
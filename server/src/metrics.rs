@@ -0,0 +1,174 @@
+//! Prometheus metrics, exposed in the text format at `/metrics`.
+
+use once_cell::sync::Lazy;
+use prometheus::{
+    exponential_buckets, Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge,
+    Opts, Registry, TextEncoder,
+};
+
+use crate::gist::GistError;
+use crate::repo::ImportError;
+
+pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+pub struct Metrics {
+    registry: Registry,
+    /// Finished compile jobs by outcome: `success`, `compile_error`, `bundle_error`, `failed`,
+    /// `timed_out` or `cancelled`.
+    pub compiles: IntCounterVec,
+    /// Cache lookups by result: `hit` or `miss`.
+    pub cache_lookups: IntCounterVec,
+    /// Duration of the build phases in seconds by phase: `cargo` or `bindgen`.
+    pub phase_duration: HistogramVec,
+    /// The number of compile jobs waiting for the workspace.
+    pub queue_depth: IntGauge,
+    /// Time in seconds that compile jobs waited for the workspace.
+    pub lock_wait: Histogram,
+    /// Size in bytes of newly built artifacts by kind: `wasm` or `js`.
+    pub artifact_size: HistogramVec,
+    /// Requests to GitHub by operation (`create` or `fetch` of a gist, `import` of a repository)
+    /// and outcome.
+    pub paste_requests: IntCounterVec,
+    /// Test runs by outcome: `finished`, `compile_error`, `run_error`, `failed` or `timed_out`.
+    pub test_runs: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("playground".to_string()), None)
+            .expect("valid registry prefix");
+        let compiles = IntCounterVec::new(
+            Opts::new("compiles_total", "Finished compile jobs by outcome."),
+            &["outcome"],
+        )
+        .unwrap();
+        let cache_lookups = IntCounterVec::new(
+            Opts::new("cache_lookups_total", "Compile cache lookups by result."),
+            &["result"],
+        )
+        .unwrap();
+        let phase_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "build_phase_duration_seconds",
+                "Duration of the cargo and wasm-bindgen build phases.",
+            )
+            .buckets(vec![
+                0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0,
+            ]),
+            &["phase"],
+        )
+        .unwrap();
+        let queue_depth = IntGauge::new(
+            "compile_queue_depth",
+            "Compile jobs waiting for the workspace.",
+        )
+        .unwrap();
+        let lock_wait = Histogram::with_opts(
+            HistogramOpts::new(
+                "compile_lock_wait_seconds",
+                "Time compile jobs waited for the workspace.",
+            )
+            .buckets(vec![0.01, 0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0]),
+        )
+        .unwrap();
+        let artifact_size = HistogramVec::new(
+            HistogramOpts::new("artifact_size_bytes", "Size of newly built artifacts.")
+                .buckets(exponential_buckets(16.0 * 1024.0, 2.0, 10).unwrap()),
+            &["kind"],
+        )
+        .unwrap();
+        let paste_requests = IntCounterVec::new(
+            Opts::new(
+                "paste_requests_total",
+                "Requests to GitHub by operation and outcome.",
+            ),
+            &["operation", "outcome"],
+        )
+        .unwrap();
+        let test_runs = IntCounterVec::new(
+            Opts::new("test_runs_total", "Test runs by outcome."),
+            &["outcome"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(compiles.clone())).unwrap();
+        registry.register(Box::new(cache_lookups.clone())).unwrap();
+        registry.register(Box::new(phase_duration.clone())).unwrap();
+        registry.register(Box::new(queue_depth.clone())).unwrap();
+        registry.register(Box::new(lock_wait.clone())).unwrap();
+        registry.register(Box::new(artifact_size.clone())).unwrap();
+        registry.register(Box::new(paste_requests.clone())).unwrap();
+        registry.register(Box::new(test_runs.clone())).unwrap();
+
+        Self {
+            registry,
+            compiles,
+            cache_lookups,
+            phase_duration,
+            queue_depth,
+            lock_wait,
+            artifact_size,
+            paste_requests,
+            test_runs,
+        }
+    }
+
+    /// Records the result of a request to the gist API.
+    pub fn record_paste<T>(&self, operation: &str, res: &Result<T, GistError>) {
+        let outcome = match res {
+            Ok(_) => "ok",
+            Err(err) => github_outcome(err),
+        };
+        self.paste_requests
+            .with_label_values(&[operation, outcome])
+            .inc();
+    }
+
+    /// Records the result of importing a repository.
+    pub fn record_import<T>(&self, res: &Result<T, ImportError>) {
+        let outcome = match res {
+            Ok(_) => "ok",
+            Err(ImportError::GitHub(err)) => github_outcome(err),
+            Err(ImportError::InvalidProject(_)) => "invalid_project",
+        };
+        self.paste_requests
+            .with_label_values(&["import", outcome])
+            .inc();
+    }
+
+    /// Renders all metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buf = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buf)
+            .expect("encoding metrics into a buffer cannot fail");
+        String::from_utf8(buf).expect("metrics are valid UTF-8")
+    }
+}
+
+/// The outcome label of a failed request to GitHub.
+fn github_outcome(err: &GistError) -> &'static str {
+    match err {
+        GistError::RateLimited => "rate_limited",
+        GistError::Unauthorized => "unauthorized",
+        GistError::NotFound => "not_found",
+        GistError::Upstream(_) => "upstream",
+    }
+}
+
+/// Increments a gauge while it is alive. Decrements it again when dropped, even if the future
+/// holding it is cancelled.
+pub struct GaugeGuard(IntGauge);
+
+impl GaugeGuard {
+    pub fn new(gauge: &IntGauge) -> Self {
+        gauge.inc();
+        Self(gauge.clone())
+    }
+}
+
+impl Drop for GaugeGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
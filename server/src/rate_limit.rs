@@ -0,0 +1,377 @@
+//! Per-client rate limiting.
+//!
+//! Clients are identified by their API key if they send a known one, and by their IP address
+//! otherwise. Every client gets a token bucket per [`RateLimitLayer`], so that compiles and pastes
+//! are limited separately. Requests that find the bucket empty are rejected with
+//! [`ApiError::RateLimited`] and a `Retry-After` header.
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use axum::extract::ConnectInfo;
+use axum::http::{header, HeaderValue, Request};
+use axum::response::{IntoResponse, Response};
+use playground_common::api::{self, ApiError};
+use tower::{Layer, Service};
+
+use crate::config::{BucketConfig, RateLimitConfig};
+use crate::error::ErrorResponse;
+
+/// The most clients that are tracked. Once there are this many, the buckets that are full are
+/// dropped, and then the least recently used ones until half of them are left, so that sweeps are
+/// rare even if all clients are limited. A client whose bucket is dropped starts with a full one.
+const MAX_BUCKETS: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ClientId {
+    ApiKey(String),
+    Ip(IpAddr),
+}
+
+/// How clients are identified and which clients are exempt. Shared by all rate limit layers.
+pub struct ClientPolicy {
+    trust_proxy: bool,
+    api_keys: HashSet<String>,
+    allow_list: HashSet<String>,
+}
+
+impl ClientPolicy {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            trust_proxy: config.trust_proxy,
+            api_keys: config.api_keys.iter().cloned().collect(),
+            allow_list: config.allow_list.iter().cloned().collect(),
+        }
+    }
+
+    /// Identifies the client that sent the request. Returns `None` if the client is allow-listed
+    /// or its address is unknown.
+    fn client_id<B>(&self, req: &Request<B>) -> Option<ClientId> {
+        let api_key = req
+            .headers()
+            .get(api::API_KEY_HEADER)
+            .and_then(|key| key.to_str().ok())
+            .filter(|key| self.api_keys.contains(*key) || self.allow_list.contains(*key));
+        if let Some(api_key) = api_key {
+            return (!self.allow_list.contains(api_key))
+                .then(|| ClientId::ApiKey(api_key.to_string()));
+        }
+
+        let forwarded_for = self
+            .trust_proxy
+            .then(|| req.headers().get("x-forwarded-for"))
+            .flatten()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok());
+        let ip = forwarded_for.or_else(|| {
+            req.extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip())
+        })?;
+        (!self.allow_list.contains(&ip.to_string())).then_some(ClientId::Ip(ip))
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct RateLimiter {
+    burst: f64,
+    tokens_per_sec: f64,
+    buckets: Mutex<HashMap<ClientId, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Takes a token from the client's bucket. Returns how long the client has to wait for the
+    /// next token if the bucket is empty.
+    fn acquire(&self, client: ClientId) -> Result<(), Duration> {
+        self.acquire_at(client, Instant::now())
+    }
+
+    fn acquire_at(&self, client: ClientId, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_BUCKETS && !buckets.contains_key(&client) {
+            buckets.retain(|_, bucket| self.refill(bucket, now) < self.burst);
+            let excess = buckets.len().saturating_sub(MAX_BUCKETS / 2);
+            if excess > 0 {
+                let mut updated: Vec<_> = buckets.values().map(|bucket| bucket.updated).collect();
+                let (_, &mut cutoff, _) = updated.select_nth_unstable(excess - 1);
+                buckets.retain(|_, bucket| bucket.updated > cutoff);
+            }
+        }
+
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        bucket.tokens = self.refill(bucket, now);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.tokens_per_sec,
+            ))
+        }
+    }
+
+    /// The number of tokens in the bucket at `now`.
+    fn refill(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.tokens_per_sec).min(self.burst)
+    }
+}
+
+/// Applies a token bucket rate limit to the wrapped service.
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: Option<Arc<RateLimiter>>,
+    policy: Arc<ClientPolicy>,
+}
+
+impl RateLimitLayer {
+    pub fn new(config: &RateLimitConfig, bucket: BucketConfig, policy: Arc<ClientPolicy>) -> Self {
+        let limiter = config.enabled.then(|| {
+            Arc::new(RateLimiter {
+                burst: f64::from(bucket.burst),
+                tokens_per_sec: f64::from(bucket.per_minute) / 60.0,
+                buckets: Mutex::default(),
+            })
+        });
+        Self { limiter, policy }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    layer: RateLimitLayer,
+}
+
+impl<S, B> Service<Request<B>> for RateLimit<S>
+where
+    S: Service<Request<B>, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let limited = match (&self.layer.limiter, self.layer.policy.client_id(&req)) {
+            (Some(limiter), Some(client)) => limiter.acquire(client).err(),
+            _ => None,
+        };
+        match limited {
+            None => Box::pin(self.inner.call(req)),
+            Some(retry_after) => {
+                let mut res = ErrorResponse(ApiError::RateLimited).into_response();
+                // Round up so that clients do not retry before the token is available.
+                let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+                res.headers_mut()
+                    .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+                Box::pin(async move { Ok(res) })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(burst: u32, per_minute: u32) -> RateLimiter {
+        RateLimiter {
+            burst: f64::from(burst),
+            tokens_per_sec: f64::from(per_minute) / 60.0,
+            buckets: Mutex::default(),
+        }
+    }
+
+    fn ip(ip: &str) -> ClientId {
+        ClientId::Ip(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn acquire_allows_burst_then_limits() {
+        let limiter = limiter(3, 60);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(limiter.acquire_at(ip("1.2.3.4"), now), Ok(()));
+        }
+        let retry_after = limiter.acquire_at(ip("1.2.3.4"), now).unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn clients_have_separate_buckets() {
+        let limiter = limiter(1, 60);
+        let now = Instant::now();
+        assert_eq!(limiter.acquire_at(ip("1.2.3.4"), now), Ok(()));
+        assert!(limiter.acquire_at(ip("1.2.3.4"), now).is_err());
+        assert_eq!(limiter.acquire_at(ip("5.6.7.8"), now), Ok(()));
+        assert_eq!(
+            limiter.acquire_at(ClientId::ApiKey("key".to_string()), now),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn refill_restores_tokens_over_time() {
+        let limiter = limiter(2, 30);
+        let start = Instant::now();
+        assert_eq!(limiter.acquire_at(ip("1.2.3.4"), start), Ok(()));
+        assert_eq!(limiter.acquire_at(ip("1.2.3.4"), start), Ok(()));
+        // One token every two seconds.
+        let retry_after = limiter
+            .acquire_at(ip("1.2.3.4"), start + Duration::from_secs(1))
+            .unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(1));
+        assert_eq!(
+            limiter.acquire_at(ip("1.2.3.4"), start + Duration::from_secs(2)),
+            Ok(())
+        );
+        assert!(limiter
+            .acquire_at(ip("1.2.3.4"), start + Duration::from_secs(2))
+            .is_err());
+    }
+
+    #[test]
+    fn least_recently_used_buckets_are_dropped() {
+        let limiter = limiter(2, 1);
+        let start = Instant::now();
+        for i in 0..MAX_BUCKETS {
+            let client = ClientId::ApiKey(i.to_string());
+            let now = start + Duration::from_millis(i as u64);
+            assert_eq!(limiter.acquire_at(client, now), Ok(()));
+        }
+        assert_eq!(limiter.buckets.lock().unwrap().len(), MAX_BUCKETS);
+
+        let now = start + Duration::from_millis(MAX_BUCKETS as u64);
+        assert_eq!(limiter.acquire_at(ip("1.2.3.4"), now), Ok(()));
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), MAX_BUCKETS / 2 + 1);
+        assert!(!buckets.contains_key(&ClientId::ApiKey("0".to_string())));
+        let newest = ClientId::ApiKey((MAX_BUCKETS - 1).to_string());
+        assert!(buckets.contains_key(&newest));
+    }
+
+    #[test]
+    fn refill_is_capped_at_burst() {
+        let limiter = limiter(2, 60);
+        let bucket = Bucket {
+            tokens: 1.0,
+            updated: Instant::now(),
+        };
+        let later = bucket.updated + Duration::from_secs(3600);
+        assert_eq!(limiter.refill(&bucket, later), 2.0);
+        let soon = bucket.updated + Duration::from_millis(500);
+        assert_eq!(limiter.refill(&bucket, soon), 1.5);
+    }
+
+    fn policy(trust_proxy: bool) -> ClientPolicy {
+        ClientPolicy::new(&RateLimitConfig {
+            trust_proxy,
+            api_keys: vec!["key".to_string()],
+            allow_list: vec!["10.0.0.1".to_string(), "trusted-key".to_string()],
+            ..RateLimitConfig::default()
+        })
+    }
+
+    fn request(peer: Option<&str>, headers: &[(&str, &str)]) -> Request<()> {
+        let mut builder = Request::builder();
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        let mut req = builder.body(()).unwrap();
+        if let Some(peer) = peer {
+            let addr = SocketAddr::new(peer.parse().unwrap(), 1234);
+            req.extensions_mut().insert(ConnectInfo(addr));
+        }
+        req
+    }
+
+    #[test]
+    fn peer_address_identifies_client() {
+        let req = request(Some("1.2.3.4"), &[]);
+        assert_eq!(policy(false).client_id(&req), Some(ip("1.2.3.4")));
+        assert_eq!(policy(false).client_id(&request(None, &[])), None);
+    }
+
+    #[test]
+    fn forwarded_for_is_ignored_without_trusted_proxy() {
+        let req = request(Some("1.2.3.4"), &[("x-forwarded-for", "5.6.7.8")]);
+        assert_eq!(policy(false).client_id(&req), Some(ip("1.2.3.4")));
+    }
+
+    #[test]
+    fn trusted_proxy_uses_last_forwarded_address() {
+        let req = request(
+            Some("10.1.1.1"),
+            &[("x-forwarded-for", "9.9.9.9, 5.6.7.8 , 2001:db8::1")],
+        );
+        assert_eq!(policy(true).client_id(&req), Some(ip("2001:db8::1")));
+        // A client can prepend addresses, but not replace the one appended by the proxy.
+        let req = request(
+            Some("10.1.1.1"),
+            &[("x-forwarded-for", "10.0.0.1, 5.6.7.8")],
+        );
+        assert_eq!(policy(true).client_id(&req), Some(ip("5.6.7.8")));
+    }
+
+    #[test]
+    fn invalid_forwarded_for_falls_back_to_peer_address() {
+        let req = request(Some("10.1.1.1"), &[("x-forwarded-for", "not an ip")]);
+        assert_eq!(policy(true).client_id(&req), Some(ip("10.1.1.1")));
+        let req = request(Some("10.1.1.1"), &[("x-forwarded-for", "")]);
+        assert_eq!(policy(true).client_id(&req), Some(ip("10.1.1.1")));
+    }
+
+    #[test]
+    fn allow_listed_addresses_are_not_limited() {
+        assert_eq!(
+            policy(false).client_id(&request(Some("10.0.0.1"), &[])),
+            None
+        );
+        let req = request(Some("10.1.1.1"), &[("x-forwarded-for", "10.0.0.1")]);
+        assert_eq!(policy(true).client_id(&req), None);
+    }
+
+    #[test]
+    fn api_keys_identify_clients() {
+        let req = request(Some("1.2.3.4"), &[(api::API_KEY_HEADER, "key")]);
+        assert_eq!(
+            policy(false).client_id(&req),
+            Some(ClientId::ApiKey("key".to_string()))
+        );
+        let req = request(Some("1.2.3.4"), &[(api::API_KEY_HEADER, "trusted-key")]);
+        assert_eq!(policy(false).client_id(&req), None);
+        // Unknown keys are limited by address, so that clients cannot get fresh buckets.
+        let req = request(Some("1.2.3.4"), &[(api::API_KEY_HEADER, "made-up")]);
+        assert_eq!(policy(false).client_id(&req), Some(ip("1.2.3.4")));
+    }
+}
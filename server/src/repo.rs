@@ -0,0 +1,1079 @@
+//! Importing projects that build with trunk from GitHub repositories.
+//!
+//! Only what the playground can run is imported: `src/main.rs` with its modules inlined, the
+//! `<head>` of `index.html`, the styles and the files that trunk copies into the output
+//! directory. Everything else is reported back as warnings. Projects are fetched from GitHub or
+//! read from an uploaded zip archive.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::body::Bytes;
+use playground_common::api::{Asset, ImportedProject};
+use playground_common::bundle::{PageHead, TAILWIND_CDN_URL};
+use serde::Deserialize;
+use tokio::fs;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::config::LimitsConfig;
+use crate::gist::GistError;
+use crate::validate::is_valid_asset_path;
+
+/// How deeply modules in other files are inlined into `main.rs`.
+const MAX_MODULE_DEPTH: usize = 8;
+
+/// How many files are fetched from GitHub at the same time.
+const MAX_CONCURRENT_FETCHES: usize = 8;
+
+/// An error that occurred while importing a project.
+#[derive(Debug)]
+pub enum ImportError {
+    /// The repository could not be fetched from GitHub.
+    GitHub(GistError),
+    /// The repository does not contain a project that can be imported.
+    InvalidProject(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GitHub(err) => write!(f, "{err}"),
+            Self::InvalidProject(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<GistError> for ImportError {
+    fn from(err: GistError) -> Self {
+        Self::GitHub(err)
+    }
+}
+
+fn invalid_project(message: impl Into<String>) -> ImportError {
+    ImportError::InvalidProject(message.into())
+}
+
+/// A project in a GitHub repository, parsed from `owner/name`, optionally followed by the path of
+/// the project in the repository.
+#[derive(Debug)]
+pub struct RepoPath {
+    owner: String,
+    name: String,
+    dir: String,
+}
+
+impl RepoPath {
+    pub fn parse(repo: &str) -> Option<Self> {
+        let mut parts = repo.trim_matches('/').splitn(3, '/');
+        let owner = parts.next()?;
+        let name = parts.next()?;
+        let dir = parts.next().unwrap_or("");
+        let is_valid_name = |s: &str| {
+            !s.is_empty()
+                && s != "."
+                && s != ".."
+                && s.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+        };
+        if !is_valid_name(owner) || !is_valid_name(name) {
+            return None;
+        }
+        if !dir.is_empty() && !is_valid_asset_path(dir) {
+            return None;
+        }
+        Some(Self {
+            owner: owner.to_string(),
+            name: name.to_string(),
+            dir: dir.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for RepoPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.owner, self.name)?;
+        if !self.dir.is_empty() {
+            write!(f, "/{}", self.dir)?;
+        }
+        Ok(())
+    }
+}
+
+/// The names of the crates that the playground workspace depends on. Imported code can only use
+/// these.
+pub async fn allowed_crates(workspace: &Path) -> Result<BTreeSet<String>> {
+    let manifest = fs::read_to_string(workspace.join("Cargo.toml"))
+        .await
+        .context("could not read workspace manifest")?;
+    dependencies(&manifest).context("could not parse workspace manifest")
+}
+
+/// The names of the dependencies in a manifest.
+fn dependencies(manifest: &str) -> Result<BTreeSet<String>> {
+    let manifest: toml::Value = toml::from_str(manifest)?;
+    Ok(manifest
+        .get("dependencies")
+        .and_then(toml::Value::as_table)
+        .map(|deps| deps.keys().cloned().collect())
+        .unwrap_or_default())
+}
+
+/// Where the files of a project are read from.
+enum Source<'a> {
+    /// A directory of a GitHub repository, whose files are fetched one by one.
+    GitHub {
+        client: reqwest::Client,
+        repo: &'a RepoPath,
+        github_token: Option<&'a str>,
+        /// The commit that the files are read from, so that they all come from the same commit
+        /// even if the repository is pushed to during the import.
+        commit: String,
+    },
+    /// An uploaded zip archive. `dir` is the directory of the project in the archive, with a
+    /// trailing `/` unless it is the root.
+    Zip {
+        archive: ZipArchive<Cursor<Bytes>>,
+        dir: String,
+    },
+}
+
+/// Reads the files of a project, keeping track of the limits.
+struct Fetcher<'a> {
+    source: Source<'a>,
+    /// The sizes of the files of the project, by their paths relative to the project directory.
+    files: HashMap<String, u64>,
+    /// The number of bytes that can still be fetched.
+    budget: u64,
+}
+
+impl<'a> Fetcher<'a> {
+    /// Lists the files of a project in a GitHub repository.
+    async fn github(
+        repo: &'a RepoPath,
+        github_token: Option<&'a str>,
+        limits: &LimitsConfig,
+    ) -> Result<Fetcher<'a>, ImportError> {
+        #[derive(Deserialize)]
+        struct GetTreeRes {
+            tree: Vec<TreeEntry>,
+            truncated: bool,
+        }
+        #[derive(Deserialize)]
+        struct TreeEntry {
+            path: String,
+            #[serde(rename = "type")]
+            kind: String,
+            #[serde(default)]
+            size: u64,
+        }
+
+        let client = reqwest::Client::new();
+        let api_get = |path: String| {
+            let mut req = client
+                .get(format!(
+                    "https://api.github.com/repos/{}/{}/{path}",
+                    repo.owner, repo.name
+                ))
+                .header("User-Agent", "sycamore-playground");
+            if let Some(token) = github_token {
+                req = req.basic_auth("sycamore-playground", Some(token));
+            }
+            req
+        };
+
+        // The media type makes GitHub respond with just the SHA of the commit.
+        let res = api_get("commits/HEAD".to_string())
+            .header("Accept", "application/vnd.github.sha")
+            .send()
+            .await
+            .map_err(GistError::from)?;
+        if !res.status().is_success() {
+            return Err(GistError::from_response(&res).into());
+        }
+        let commit = res
+            .text()
+            .await
+            .map_err(GistError::from)?
+            .trim()
+            .to_string();
+        if commit.is_empty() || !commit.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(GistError::Upstream(format!(
+                "GitHub returned an invalid commit SHA {commit:?}"
+            ))
+            .into());
+        }
+
+        let res = api_get(format!("git/trees/{commit}?recursive=1"))
+            .send()
+            .await
+            .map_err(GistError::from)?;
+        if !res.status().is_success() {
+            return Err(GistError::from_response(&res).into());
+        }
+        let tree = res.json::<GetTreeRes>().await.map_err(|err| {
+            GistError::Upstream(format!("could not parse GitHub API response: {err}"))
+        })?;
+        if tree.truncated {
+            return Err(invalid_project("the repository is too large to import"));
+        }
+
+        let prefix = if repo.dir.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", repo.dir)
+        };
+        let files = tree
+            .tree
+            .into_iter()
+            .filter(|entry| entry.kind == "blob")
+            .filter_map(|entry| {
+                let path = entry.path.strip_prefix(&prefix)?.to_string();
+                Some((path, entry.size))
+            })
+            .collect();
+        Ok(Self {
+            source: Source::GitHub {
+                client,
+                repo,
+                github_token,
+                commit,
+            },
+            files,
+            budget: limits.max_payload_bytes,
+        })
+    }
+
+    /// Lists the files of a project in a zip archive. The project is in the directory with the
+    /// `Cargo.toml` that is closest to the root, so that archives of a whole repository work too.
+    fn zip(zip: Bytes, limits: &LimitsConfig) -> Result<Fetcher<'static>, ImportError> {
+        let unreadable = |err| invalid_project(format!("the archive could not be read: {err}"));
+        let mut archive = ZipArchive::new(Cursor::new(zip)).map_err(unreadable)?;
+        let mut entries = Vec::new();
+        for index in 0..archive.len() {
+            let file = archive.by_index_raw(index).map_err(unreadable)?;
+            if file.is_file() && is_enclosed(file.name()) {
+                entries.push((file.name().to_string(), file.size()));
+            }
+        }
+        let dir = entries
+            .iter()
+            .filter_map(|(name, _)| name.strip_suffix("Cargo.toml"))
+            .filter(|dir| dir.is_empty() || dir.ends_with('/'))
+            .min_by_key(|dir| (dir.matches('/').count(), dir.to_string()))
+            .ok_or_else(|| invalid_project("the archive does not contain a `Cargo.toml`"))?
+            .to_string();
+        let files = entries
+            .into_iter()
+            .filter_map(|(name, size)| Some((name.strip_prefix(&dir)?.to_string(), size)))
+            .collect();
+        Ok(Fetcher {
+            source: Source::Zip { archive, dir },
+            files,
+            budget: limits.max_payload_bytes,
+        })
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// The paths of the files in a directory of the project and its subdirectories, sorted.
+    fn files_in(&self, dir: &str) -> Vec<String> {
+        let prefix = format!("{dir}/");
+        let mut paths: Vec<_> = self
+            .files
+            .keys()
+            .filter(|path| path.starts_with(&prefix))
+            .cloned()
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    async fn fetch(&mut self, path: &str) -> Result<Vec<u8>, ImportError> {
+        let mut files = self.fetch_all(&[path.to_string()]).await?;
+        Ok(files.remove(0))
+    }
+
+    async fn fetch_string(&mut self, path: &str) -> Result<String, ImportError> {
+        into_string(path, self.fetch(path).await?)
+    }
+
+    /// Fetches several files, in the order of `paths`. Files in a GitHub repository are fetched
+    /// concurrently, so that projects with many files can be imported within the timeout.
+    async fn fetch_all(&mut self, paths: &[String]) -> Result<Vec<Vec<u8>>, ImportError> {
+        let mut sizes = Vec::with_capacity(paths.len());
+        for path in paths {
+            let Some(&size) = self.files.get(path) else {
+                return Err(invalid_project(format!("`{path}` does not exist")));
+            };
+            // Everything that is imported has to fit into a compile request later.
+            self.budget = self.budget.checked_sub(size).ok_or_else(|| {
+                invalid_project("the project is too large to be built in the playground")
+            })?;
+            sizes.push(size);
+        }
+
+        match &mut self.source {
+            Source::GitHub {
+                client,
+                repo,
+                github_token,
+                commit,
+            } => {
+                let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_FETCHES));
+                let mut tasks = JoinSet::new();
+                for (index, (path, size)) in paths.iter().zip(sizes).enumerate() {
+                    let full_path = if repo.dir.is_empty() {
+                        path.to_string()
+                    } else {
+                        format!("{}/{path}", repo.dir)
+                    };
+                    let mut req = client
+                        .get(format!(
+                            "https://raw.githubusercontent.com/{}/{}/{commit}/{full_path}",
+                            repo.owner, repo.name
+                        ))
+                        .header("User-Agent", "sycamore-playground");
+                    if let Some(token) = github_token {
+                        req = req.basic_auth("sycamore-playground", Some(token));
+                    }
+                    let permits = permits.clone();
+                    let path = path.clone();
+                    tasks.spawn(async move {
+                        let _permit = permits.acquire_owned().await.expect("never closed");
+                        let mut res = req.send().await.map_err(GistError::from)?;
+                        if !res.status().is_success() {
+                            return Err(GistError::from_response(&res).into());
+                        }
+                        // Like the sizes in a zip archive, the size in the tree is not trusted.
+                        // Reading stops as soon as the body is larger.
+                        let mut data = Vec::new();
+                        while let Some(chunk) = res.chunk().await.map_err(GistError::from)? {
+                            data.extend_from_slice(&chunk);
+                            if data.len() as u64 > size {
+                                return Err(invalid_project(format!(
+                                    "`{path}` is larger than its size in the repository"
+                                )));
+                            }
+                        }
+                        Ok::<_, ImportError>((index, data))
+                    });
+                }
+                let mut files = vec![Vec::new(); paths.len()];
+                // Dropping the set aborts the other fetches if one of them fails.
+                while let Some(res) = tasks.join_next().await {
+                    let (index, data) = res.expect("fetch tasks do not panic")?;
+                    files[index] = data;
+                }
+                Ok(files)
+            }
+            Source::Zip { archive, dir } => paths
+                .iter()
+                .zip(sizes)
+                .map(|(path, size)| {
+                    let read = |archive: &mut ZipArchive<Cursor<Bytes>>| {
+                        let file = archive.by_name(&format!("{dir}{path}"))?;
+                        let mut data = Vec::new();
+                        // The size in the archive is not trusted, a file may decompress to more.
+                        file.take(size + 1).read_to_end(&mut data)?;
+                        Ok::<_, ZipError>(data)
+                    };
+                    match read(archive) {
+                        Ok(data) if data.len() as u64 <= size => Ok(data),
+                        Ok(_) => Err(invalid_project(format!(
+                            "`{path}` is larger than its size in the archive"
+                        ))),
+                        Err(err) => Err(invalid_project(format!(
+                            "`{path}` could not be read from the archive: {err}"
+                        ))),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Whether the name of a file in a zip archive is a relative path that stays in the archive.
+fn is_enclosed(name: &str) -> bool {
+    !name.starts_with('/')
+        && !name.contains('\\')
+        && name
+            .split('/')
+            .all(|segment| !segment.is_empty() && segment != "." && segment != "..")
+}
+
+fn into_string(path: &str, data: Vec<u8>) -> Result<String, ImportError> {
+    String::from_utf8(data).map_err(|_| invalid_project(format!("`{path}` is not valid UTF-8")))
+}
+
+/// Imports the project in `repo`. The dependencies of the project that are not in
+/// `allowed_crates` are reported as unsupported.
+pub async fn import(
+    repo: &RepoPath,
+    github_token: Option<&str>,
+    limits: &LimitsConfig,
+    allowed_crates: &BTreeSet<String>,
+) -> Result<ImportedProject<'static>, ImportError> {
+    let fetcher = Fetcher::github(repo, github_token, limits).await?;
+    import_project(fetcher, &format!("`{repo}`"), limits, allowed_crates).await
+}
+
+/// Imports the project in an uploaded zip archive, like [`import`].
+pub async fn import_zip(
+    zip: Bytes,
+    limits: &LimitsConfig,
+    allowed_crates: &BTreeSet<String>,
+) -> Result<ImportedProject<'static>, ImportError> {
+    let fetcher = Fetcher::zip(zip, limits)?;
+    import_project(
+        fetcher,
+        "the project in the archive",
+        limits,
+        allowed_crates,
+    )
+    .await
+}
+
+/// Imports a project from its files. `name` describes the project in error messages.
+async fn import_project(
+    mut fetcher: Fetcher<'_>,
+    name: &str,
+    limits: &LimitsConfig,
+    allowed_crates: &BTreeSet<String>,
+) -> Result<ImportedProject<'static>, ImportError> {
+    if !fetcher.contains("Cargo.toml") || !fetcher.contains("src/main.rs") {
+        return Err(invalid_project(format!(
+            "{name} is not a binary crate, it needs a `Cargo.toml` and a `src/main.rs`"
+        )));
+    }
+    let mut warnings = Vec::new();
+
+    let manifest = fetcher.fetch_string("Cargo.toml").await?;
+    let unsupported_dependencies = dependencies(&manifest)
+        .map_err(|err| invalid_project(format!("could not parse `Cargo.toml`: {err}")))?
+        .difference(allowed_crates)
+        .cloned()
+        .collect();
+
+    let paths: Vec<_> = fetcher
+        .files_in("src")
+        .into_iter()
+        .filter(|path| path.ends_with(".rs"))
+        .collect();
+    let mut sources = HashMap::new();
+    for (path, data) in paths.iter().zip(fetcher.fetch_all(&paths).await?) {
+        sources.insert(path.clone(), into_string(path, data)?);
+    }
+    let mut inlined = HashSet::new();
+    let code = inline_modules(
+        &sources,
+        "src/main.rs",
+        "src",
+        0,
+        &mut inlined,
+        &mut warnings,
+    );
+    let mut unused: Vec<_> = sources
+        .keys()
+        .filter(|path| !inlined.contains(path.as_str()))
+        .collect();
+    unused.sort();
+    for path in unused {
+        warnings.push(format!(
+            "`{path}` was not imported because it is not a module of `src/main.rs`"
+        ));
+    }
+    if code.len() as u64 > limits.max_code_bytes {
+        return Err(invalid_project(format!(
+            "the code is too large, the limit is {} bytes",
+            limits.max_code_bytes
+        )));
+    }
+
+    let mut head = PageHead::default();
+    let mut assets = Vec::new();
+    if fetcher.contains("index.html") {
+        let index_html = fetcher.fetch_string("index.html").await?;
+        let parsed = parse_head(&index_html);
+        head.html = parsed.html;
+        head.css = parsed.css;
+        head.tailwind = parsed.tailwind;
+        warnings.extend(parsed.warnings);
+        for link in parsed.links {
+            import_link(&mut fetcher, link, &mut head, &mut assets, &mut warnings).await?;
+        }
+    } else {
+        warnings.push("the project has no `index.html`".to_string());
+    }
+    // The code is a file as well.
+    if assets.len() + 1 > limits.max_files {
+        return Err(invalid_project(format!(
+            "the project has too many files, at most {} are allowed",
+            limits.max_files
+        )));
+    }
+
+    Ok(ImportedProject {
+        code: code.into(),
+        head,
+        assets,
+        unsupported_dependencies,
+        warnings,
+    })
+}
+
+/// Resolves a `<link data-trunk>` tag of `index.html`.
+async fn import_link(
+    fetcher: &mut Fetcher<'_>,
+    link: TrunkLink,
+    head: &mut PageHead,
+    assets: &mut Vec<Asset<'static>>,
+    warnings: &mut Vec<String>,
+) -> Result<(), ImportError> {
+    let TrunkLink { rel, href } = link;
+    if rel == "rust" {
+        // The playground builds the code itself.
+        return Ok(());
+    }
+    let Some(href) = href.as_deref().and_then(normalize_href) else {
+        warnings.push(format!(
+            "`<link data-trunk rel=\"{rel}\">` was ignored because its `href` is not a path in the project"
+        ));
+        return Ok(());
+    };
+    match rel.as_str() {
+        "css" | "tailwind-css" => {
+            if !head.css.is_empty() {
+                head.css.push('\n');
+            }
+            head.css.push_str(&fetcher.fetch_string(&href).await?);
+            head.tailwind |= rel == "tailwind-css";
+        }
+        "copy-file" => {
+            // Trunk copies the file into the root of the output directory.
+            let name = href.rsplit('/').next().unwrap_or(&href);
+            add_asset(assets, name, fetcher.fetch(&href).await?);
+        }
+        "copy-dir" => {
+            // Trunk copies the directory into the root of the output directory.
+            let name = href.rsplit('/').next().unwrap_or(&href);
+            let mut paths = Vec::new();
+            let mut asset_paths = Vec::new();
+            for path in fetcher.files_in(&href) {
+                let asset_path = format!("{name}/{}", &path[href.len() + 1..]);
+                if is_valid_asset_path(&asset_path) {
+                    paths.push(path);
+                    asset_paths.push(asset_path);
+                } else {
+                    warnings.push(format!(
+                        "`{path}` was not imported because assets can only have letters, digits, \
+                         `.`, `-` and `_` in their paths"
+                    ));
+                }
+            }
+            for (asset_path, data) in asset_paths.iter().zip(fetcher.fetch_all(&paths).await?) {
+                add_asset(assets, asset_path, data);
+            }
+        }
+        _ => warnings.push(format!(
+            "`<link data-trunk rel=\"{rel}\">` is not supported by the playground"
+        )),
+    }
+    Ok(())
+}
+
+/// Normalizes the `href` of a `<link data-trunk>` tag into a path in the project. Returns `None`
+/// if it is not one, e.g. because it is absolute or leaves the project directory.
+fn normalize_href(href: &str) -> Option<String> {
+    let href = href.trim_start_matches("./").trim_end_matches('/');
+    is_valid_asset_path(href).then(|| href.to_string())
+}
+
+/// Adds an asset, replacing an earlier one with the same path like trunk does.
+fn add_asset(assets: &mut Vec<Asset<'static>>, path: &str, data: Vec<u8>) {
+    assets.retain(|asset| asset.path != path);
+    assets.push(Asset {
+        path: path.to_string().into(),
+        data: data.into(),
+    });
+}
+
+/// Replaces the `mod name;` declarations in the file at `path` with the contents of the files
+/// that they refer to. `dir` is the directory that the modules of the file are in. The paths of
+/// the inlined files are added to `inlined`.
+fn inline_modules(
+    sources: &HashMap<String, String>,
+    path: &str,
+    dir: &str,
+    depth: usize,
+    inlined: &mut HashSet<String>,
+    warnings: &mut Vec<String>,
+) -> String {
+    inlined.insert(path.to_string());
+    let source = &sources[path];
+    let mut code = String::with_capacity(source.len());
+    for line in source.lines() {
+        let Some((indent, vis, name)) = parse_mod_declaration(line) else {
+            code.push_str(line);
+            code.push('\n');
+            continue;
+        };
+        let candidates = [format!("{dir}/{name}.rs"), format!("{dir}/{name}/mod.rs")];
+        let Some(file) = candidates
+            .into_iter()
+            .find(|candidate| sources.contains_key(candidate))
+        else {
+            warnings.push(format!(
+                "the file of module `{name}` in `{path}` was not found"
+            ));
+            code.push_str(line);
+            code.push('\n');
+            continue;
+        };
+        if depth >= MAX_MODULE_DEPTH || inlined.contains(&file) {
+            warnings.push(format!("module `{name}` in `{path}` was not inlined"));
+            code.push_str(line);
+            code.push('\n');
+            continue;
+        }
+        let module_dir = format!("{dir}/{name}");
+        let module = inline_modules(sources, &file, &module_dir, depth + 1, inlined, warnings);
+        code.push_str(&format!("{indent}{vis}mod {name} {{\n{module}{indent}}}\n"));
+    }
+    code
+}
+
+/// Parses a line that consists of a `mod name;` declaration into its indentation, its visibility
+/// with a trailing space, and the name of the module.
+fn parse_mod_declaration(line: &str) -> Option<(&str, &str, &str)> {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    let declaration = trimmed.trim_end().strip_suffix(';')?;
+    let mod_start = declaration.find("mod ")?;
+    let vis = &declaration[..mod_start];
+    let is_vis = vis.is_empty()
+        || vis == "pub "
+        || (vis.starts_with("pub(") && vis.trim_end().ends_with(')'));
+    let name = declaration[mod_start + "mod ".len()..].trim();
+    let is_ident = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    (is_vis && is_ident).then_some((indent, vis, name))
+}
+
+/// A `<link data-trunk>` tag in `index.html`.
+#[derive(Debug)]
+struct TrunkLink {
+    rel: String,
+    href: Option<String>,
+}
+
+/// The parts of the `<head>` of `index.html`.
+#[derive(Debug, Default)]
+struct ParsedHead {
+    /// The markup that is kept in the playground's `index.html`.
+    html: String,
+    /// The contents of the `<style>` tags.
+    css: String,
+    tailwind: bool,
+    links: Vec<TrunkLink>,
+    warnings: Vec<String>,
+}
+
+/// Splits the `<head>` of `index.html` into markup that the playground keeps, styles, and the
+/// links that trunk processes. This is not a full HTML parser, but handles what trunk projects
+/// usually contain.
+fn parse_head(index_html: &str) -> ParsedHead {
+    let mut parsed = ParsedHead::default();
+    // Lowercasing ASCII keeps the byte offsets the same.
+    let lower = index_html.to_ascii_lowercase();
+    let Some(head_start) = lower
+        .find("<head")
+        .and_then(|start| Some(start + lower[start..].find('>')? + 1))
+    else {
+        parsed
+            .warnings
+            .push("`index.html` has no `<head>`".to_string());
+        return parsed;
+    };
+    let head_end = lower[head_start..]
+        .find("</head>")
+        .map_or(lower.len(), |end| head_start + end);
+
+    let mut pos = head_start;
+    while let Some(offset) = lower[pos..head_end].find('<') {
+        let start = pos + offset;
+        parsed.html.push_str(&index_html[pos..start]);
+        let Some(tag_end) = lower[start..head_end].find('>').map(|end| start + end + 1) else {
+            pos = start;
+            break;
+        };
+        let tag = &index_html[start..tag_end];
+        let tag_lower = &lower[start..tag_end];
+        pos = tag_end;
+
+        if tag_lower.starts_with("<!--") {
+            // Comments are kept, including the tags in them.
+            let end = lower[start..head_end]
+                .find("-->")
+                .map_or(head_end, |end| start + end + "-->".len());
+            parsed.html.push_str(&index_html[start..end]);
+            pos = end;
+        } else if tag_lower.starts_with("<link") && tag_lower.contains("data-trunk") {
+            parsed.links.push(TrunkLink {
+                rel: attribute(tag, "rel").unwrap_or_default().to_string(),
+                href: attribute(tag, "href").map(str::to_string),
+            });
+        } else if tag_lower.starts_with("<style") {
+            let content_end = lower[tag_end..head_end]
+                .find("</style>")
+                .map_or(head_end, |end| tag_end + end);
+            if attribute(tag, "type") == Some("text/tailwindcss") {
+                parsed.tailwind = true;
+            }
+            let css = dedent(&index_html[tag_end..content_end]);
+            if !parsed.css.is_empty() {
+                parsed.css.push('\n');
+            }
+            parsed.css.push_str(&css);
+            pos = (content_end + "</style>".len()).min(head_end);
+        } else if tag_lower.starts_with("<script")
+            && attribute(tag, "src").is_some_and(|src| src.starts_with(TAILWIND_CDN_URL))
+        {
+            // The playground adds the Tailwind Play CDN itself.
+            parsed.tailwind = true;
+            pos = lower[tag_end..head_end]
+                .find("</script>")
+                .map_or(head_end, |end| tag_end + end + "</script>".len());
+        } else {
+            parsed.html.push_str(tag);
+        }
+    }
+    parsed.html.push_str(&index_html[pos..head_end]);
+    parsed.html = trim_lines(&parsed.html);
+    parsed
+}
+
+/// The value of an attribute of an HTML tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search = 0;
+    while let Some(offset) = lower[search..].find(name) {
+        let start = search + offset;
+        search = start + name.len();
+        let preceded_by_space = lower[..start].ends_with(|c: char| c.is_ascii_whitespace());
+        let rest = lower[search..].trim_start();
+        if !preceded_by_space || !rest.starts_with('=') {
+            continue;
+        }
+        let value_start = lower.len() - rest[1..].trim_start().len();
+        let quote = tag[value_start..].chars().next()?;
+        return if quote == '"' || quote == '\'' {
+            let value = &tag[value_start + 1..];
+            value.find(quote).map(|end| &value[..end])
+        } else {
+            let value = &tag[value_start..];
+            let end = value
+                .find(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/')
+                .unwrap_or(value.len());
+            Some(&value[..end])
+        };
+    }
+    None
+}
+
+/// Removes blank lines and the indentation that all lines share.
+fn trim_lines(html: &str) -> String {
+    let lines: Vec<_> = html
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    dedent(&lines.join("\n"))
+}
+
+/// Removes the indentation that all lines share, trailing whitespace, and blank lines at the start
+/// and the end.
+fn dedent(text: &str) -> String {
+    let indent = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+    text.lines()
+        .map(|line| line.get(indent..).unwrap_or_default().trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_matches('\n')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    use super::*;
+
+    #[test]
+    fn attributes() {
+        let tag = r#"<link data-trunk rel="copy-dir" HREF='assets/' data-x=1>"#;
+        assert_eq!(attribute(tag, "rel"), Some("copy-dir"));
+        assert_eq!(attribute(tag, "href"), Some("assets/"));
+        assert_eq!(attribute(tag, "data-x"), Some("1"));
+        assert_eq!(attribute(tag, "type"), None);
+        // `rel` is part of another attribute name here.
+        assert_eq!(attribute(r#"<link data-rel="x">"#, "rel"), None);
+        assert_eq!(attribute("<link rel = css/>", "rel"), Some("css"));
+        assert_eq!(attribute(r#"<link data-trunk>"#, "data-trunk"), None);
+    }
+
+    #[test]
+    fn head_is_split_into_markup_styles_and_links() {
+        let index_html = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    <title>App</title>
+    <link data-trunk rel="rust" />
+    <link data-trunk rel="css" href="./style.css">
+    <!-- <link data-trunk rel="copy-dir" href="ignored"> -->
+    <style>
+      body {
+        margin: 0;
+      }
+
+      h1 { color: red; }
+    </style>
+  </head>
+  <body></body>
+</html>
+"#;
+        let parsed = parse_head(index_html);
+        assert_eq!(
+            parsed.html,
+            "<meta charset=\"utf-8\">\n<title>App</title>\n\
+             <!-- <link data-trunk rel=\"copy-dir\" href=\"ignored\"> -->"
+        );
+        assert_eq!(parsed.css, "body {\n  margin: 0;\n}\n\nh1 { color: red; }");
+        assert!(!parsed.tailwind);
+        let links: Vec<_> = parsed
+            .links
+            .iter()
+            .map(|link| (link.rel.as_str(), link.href.as_deref()))
+            .collect();
+        assert_eq!(links, [("rust", None), ("css", Some("./style.css"))]);
+        assert!(parsed.warnings.is_empty());
+    }
+
+    #[test]
+    fn tailwind_is_detected() {
+        let cdn = format!(r#"<head><script src="{TAILWIND_CDN_URL}"></script></head>"#);
+        let parsed = parse_head(&cdn);
+        assert!(parsed.tailwind);
+        assert_eq!(parsed.html, "");
+
+        let style = r#"<head><style type="text/tailwindcss">@tailwind base;</style></head>"#;
+        let parsed = parse_head(style);
+        assert!(parsed.tailwind);
+        assert_eq!(parsed.css, "@tailwind base;");
+    }
+
+    #[test]
+    fn missing_head_is_reported() {
+        let parsed = parse_head("<html><body></body></html>");
+        assert_eq!(parsed.warnings, ["`index.html` has no `<head>`"]);
+    }
+
+    fn sources(files: &[(&str, &str)]) -> HashMap<String, String> {
+        files
+            .iter()
+            .map(|(path, source)| (path.to_string(), source.to_string()))
+            .collect()
+    }
+
+    fn inline(sources: &HashMap<String, String>) -> (String, HashSet<String>, Vec<String>) {
+        let mut inlined = HashSet::new();
+        let mut warnings = Vec::new();
+        let code = inline_modules(
+            sources,
+            "src/main.rs",
+            "src",
+            0,
+            &mut inlined,
+            &mut warnings,
+        );
+        (code, inlined, warnings)
+    }
+
+    #[test]
+    fn modules_are_inlined() {
+        let sources = sources(&[
+            (
+                "src/main.rs",
+                "mod app;\npub(crate) mod util;\n\nfn main() {}\n",
+            ),
+            ("src/app.rs", "mod view;\npub fn app() {}\n"),
+            ("src/app/view.rs", "pub fn view() {}\n"),
+            ("src/util/mod.rs", "pub fn util() {}\n"),
+        ]);
+        let (code, inlined, warnings) = inline(&sources);
+        assert_eq!(
+            code,
+            "mod app {\nmod view {\npub fn view() {}\n}\npub fn app() {}\n}\n\
+             pub(crate) mod util {\npub fn util() {}\n}\n\nfn main() {}\n"
+        );
+        assert_eq!(inlined.len(), 4);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn missing_modules_are_kept() {
+        let sources = sources(&[
+            ("src/main.rs", "mod missing;\nmod inline {}\nmod app;\n"),
+            ("src/app.rs", "mod view;\n"),
+        ]);
+        let (code, _, warnings) = inline(&sources);
+        assert_eq!(
+            code,
+            "mod missing;\nmod inline {}\nmod app {\nmod view;\n}\n"
+        );
+        assert_eq!(
+            warnings,
+            [
+                "the file of module `missing` in `src/main.rs` was not found",
+                "the file of module `view` in `src/app.rs` was not found",
+            ]
+        );
+    }
+
+    #[test]
+    fn mod_declarations() {
+        assert_eq!(parse_mod_declaration("mod app;"), Some(("", "", "app")));
+        assert_eq!(
+            parse_mod_declaration("    pub mod app;"),
+            Some(("    ", "pub ", "app"))
+        );
+        assert_eq!(
+            parse_mod_declaration("pub(super) mod app_2;"),
+            Some(("", "pub(super) ", "app_2"))
+        );
+        assert_eq!(parse_mod_declaration("mod app {}"), None);
+        assert_eq!(parse_mod_declaration("use app::mod x;"), None);
+        assert_eq!(parse_mod_declaration("mod 2app;"), None);
+    }
+
+    #[test]
+    fn hrefs_must_stay_in_the_project() {
+        assert_eq!(normalize_href("./assets/"), Some("assets".to_string()));
+        assert_eq!(
+            normalize_href("img/logo.svg"),
+            Some("img/logo.svg".to_string())
+        );
+        assert_eq!(normalize_href("../secret"), None);
+        assert_eq!(normalize_href("assets/../../secret"), None);
+        assert_eq!(normalize_href("/etc/passwd"), None);
+        assert_eq!(normalize_href("assets//logo.svg"), None);
+        assert_eq!(normalize_href(""), None);
+        assert_eq!(normalize_href("https://example.com/style.css"), None);
+    }
+
+    #[test]
+    fn zip_entries_must_stay_in_the_archive() {
+        assert!(is_enclosed("project/src/main.rs"));
+        assert!(!is_enclosed("/src/main.rs"));
+        assert!(!is_enclosed("project/../../main.rs"));
+        assert!(!is_enclosed("project//main.rs"));
+        assert!(!is_enclosed("project\\main.rs"));
+    }
+
+    fn zip(files: &[(&str, &str)]) -> Bytes {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, data) in files {
+            zip.start_file(*path, FileOptions::default()).unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner().into()
+    }
+
+    #[tokio::test]
+    async fn import_from_zip() {
+        // Like the archives that GitHub creates, with the project in a directory.
+        let archive = zip(&[
+            ("repo-main/README.md", "# App"),
+            ("repo-main/examples/other/Cargo.toml", ""),
+            (
+                "repo-main/Cargo.toml",
+                "[dependencies]\nsycamore = \"0.8\"\nrand = \"0.8\"\n",
+            ),
+            ("repo-main/src/main.rs", "mod app;\nfn main() {}\n"),
+            ("repo-main/src/app.rs", "pub fn app() {}\n"),
+            ("repo-main/src/unused.rs", ""),
+            (
+                "repo-main/index.html",
+                "<head>\n<link data-trunk rel=\"copy-dir\" href=\"assets\">\n\
+                 <link data-trunk rel=\"copy-file\" href=\"../secret\">\n</head>",
+            ),
+            ("repo-main/assets/data.json", "{}"),
+            ("repo-main/assets/my file.txt", ""),
+        ]);
+        let allowed_crates = BTreeSet::from(["sycamore".to_string()]);
+        let project = import_zip(archive, &LimitsConfig::default(), &allowed_crates)
+            .await
+            .unwrap();
+        assert_eq!(
+            project.code,
+            "mod app {\npub fn app() {}\n}\nfn main() {}\n"
+        );
+        assert_eq!(project.unsupported_dependencies, ["rand"]);
+        let assets: Vec<_> = project.assets.iter().map(|a| a.path.as_ref()).collect();
+        assert_eq!(assets, ["assets/data.json"]);
+        assert_eq!(project.warnings.len(), 3, "{:?}", project.warnings);
+    }
+
+    #[tokio::test]
+    async fn zip_without_a_binary_crate() {
+        let limits = LimitsConfig::default();
+        let allowed_crates = BTreeSet::new();
+        let err = import_zip(zip(&[("README.md", "")]), &limits, &allowed_crates)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ImportError::InvalidProject(_)));
+        let err = import_zip(zip(&[("Cargo.toml", "")]), &limits, &allowed_crates)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not a binary crate"));
+        let err = import_zip(Bytes::from_static(b"not a zip"), &limits, &allowed_crates)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("could not be read"));
+    }
+
+    #[tokio::test]
+    async fn zip_over_the_budget() {
+        let limits = LimitsConfig {
+            max_payload_bytes: 16,
+            ..LimitsConfig::default()
+        };
+        let archive = zip(&[
+            ("Cargo.toml", ""),
+            ("src/main.rs", "fn main() { println!(\"too large\"); }"),
+        ]);
+        let err = import_zip(archive, &limits, &BTreeSet::new())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("too large"));
+    }
+}
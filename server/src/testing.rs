@@ -0,0 +1,756 @@
+//! Running the tests in user code.
+//!
+//! The tests are built in the workspace like compiles, once for the host and once for
+//! `wasm32-unknown-unknown`. `#[test]` functions run in the native test binary. `#[wasm_bindgen_test]`
+//! functions run in Node.js with the harness of `wasm-bindgen-test`, set up the way
+//! `wasm-bindgen-test-runner` does it, but with wasm-bindgen running in-process like for compiles.
+//!
+//! This executes user code on the host. Every test runs in a process of its own with resource
+//! limits, in the sandbox of [`SandboxConfig`](crate::config::SandboxConfig).
+
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use playground_common::api::{TestKind, TestOutcome, TestResponse, TestResult};
+use tokio::fs;
+use tokio::sync::Mutex;
+use tracing::{debug, info};
+use wasm_bindgen_cli_support::Bindgen;
+use wasmparser::{Parser, Payload};
+
+use crate::compile::{run, run_with_output_limit, BuildDir, WORKSPACE_LOCK};
+use crate::config::Config;
+
+/// The name of the crate in the workspace. The wasm test harness prefixes test names with it.
+const CRATE_NAME: &str = "playground";
+
+/// The prefix of the exports of the wasm test binary that run a test.
+const WASM_TEST_EXPORT_PREFIX: &str = "__wbgt_";
+
+/// The custom section that `wasm_bindgen_test_configure!` adds to tests that run in a browser or
+/// a worker.
+const BROWSER_TEST_SECTION: &str = "__wasm_bindgen_test_unstable";
+
+/// The name that wasm-bindgen gives to the generated files of the wasm test binary.
+const WASM_TEST_MODULE: &str = "wasm-bindgen-test";
+
+/// How much the tests may print to stdout and to stderr before they are stopped.
+const MAX_OUTPUT_BYTES: u64 = 1024 * 1024;
+
+/// How large the files that the tests write may be.
+const MAX_FILE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// How much address space a native test binary may use. libtest keeps the output of a test in
+/// memory, so a test that prints without end would use up the memory of the server otherwise.
+const MAX_NATIVE_MEMORY_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Where the build directory is mounted, read-only, in the sandbox.
+const SANDBOX_BUILD_DIR: &str = "/sandbox";
+
+/// Held while test binaries run. They run outside of the workspace, so builds can go on.
+static TEST_RUN_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Builds and runs the tests in `code`.
+#[tracing::instrument(skip_all)]
+pub async fn run_tests(config: &Config, code: &str) -> Result<TestResponse> {
+    static RUN_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let build_dir = BuildDir::create(
+        config,
+        &format!("test-{}", RUN_COUNTER.fetch_add(1, Ordering::Relaxed)),
+    )
+    .await?;
+    let native_tests = build_dir.path().join("native-tests");
+    let wasm_tests = build_dir.path().join("tests.wasm");
+    {
+        let _guard = WORKSPACE_LOCK.lock().await;
+        debug!("acquired workspace");
+        let workspace = &config.workspace;
+        fs::write(workspace.join("src/main.rs"), code.as_bytes()).await?;
+        for (target, output) in [
+            (None, &native_tests),
+            (Some("wasm32-unknown-unknown"), &wasm_tests),
+        ] {
+            match build_tests(workspace, target).await? {
+                Ok(binary) => {
+                    fs::copy(&binary, output)
+                        .await
+                        .context("could not copy test binary")?;
+                }
+                Err(stderr) => return Ok(TestResponse::CompileError(stderr)),
+            }
+        }
+    }
+
+    // Like builds, test runs execute one at a time. Every test runs in a process of its own, so
+    // that a test cannot fake the results of other tests by printing lines that look like them.
+    let _guard = TEST_RUN_LOCK.lock().await;
+    let sandbox = Sandbox::new(config, build_dir.path()).await?;
+    let sandboxed_tests = Path::new(SANDBOX_BUILD_DIR).join("native-tests");
+    let mut results = Vec::new();
+    let mut output = String::new();
+
+    let mut command = sandbox.command(&sandboxed_tests, Some(MAX_NATIVE_MEMORY_BYTES));
+    command.arg("--list").arg("--format").arg("terse");
+    let Some(list) = run_with_output_limit(command, MAX_OUTPUT_BYTES)
+        .await
+        .context("could not list native tests")?
+    else {
+        return Ok(output_too_large());
+    };
+    for name in parse_test_list(&String::from_utf8_lossy(&list.stdout)) {
+        let mut command = sandbox.command(&sandboxed_tests, Some(MAX_NATIVE_MEMORY_BYTES));
+        command
+            .arg(&name)
+            .arg("--exact")
+            .arg("--test-threads=1")
+            .arg("--color")
+            .arg("never");
+        let limit = MAX_OUTPUT_BYTES.saturating_sub(output.len() as u64);
+        let Some(run) = run_with_output_limit(command, limit)
+            .await
+            .context("could not run native tests")?
+        else {
+            return Ok(output_too_large());
+        };
+        results.push(test_result(&run, &mut output, Expected::Path(&name)));
+    }
+
+    let wasm = fs::read(&wasm_tests).await?;
+    let (tests, runs_in_browser) = wasm_test_exports(&wasm)?;
+    if runs_in_browser {
+        return Ok(TestResponse::RunError(
+            "tests configured with `wasm_bindgen_test_configure!` to run in a browser are not \
+             supported, the playground runs them in Node.js"
+                .to_string(),
+        ));
+    }
+    if !tests.is_empty() {
+        let out_dir = build_dir.path().to_path_buf();
+        let bindgen = tokio::task::spawn_blocking(move || {
+            let mut bindgen = Bindgen::new();
+            bindgen
+                .input_bytes(WASM_TEST_MODULE, wasm)
+                .nodejs(true)?
+                .debug(true)
+                .keep_debug(false)
+                .emit_start(false)
+                .generate(out_dir)
+        })
+        .await?;
+        if let Err(err) = bindgen {
+            // Formatted like the errors of the wasm-bindgen CLI.
+            return Ok(TestResponse::RunError(format!("error: {err:?}")));
+        }
+        fs::write(build_dir.path().join("run.js"), node_script()).await?;
+        let script = Path::new(SANDBOX_BUILD_DIR).join("run.js");
+        for export in &tests {
+            // Node.js reserves more address space for wasm than any sensible limit.
+            let mut command = sandbox.command("node", None);
+            command.arg("--expose-gc").arg(&script).arg(export);
+            let limit = MAX_OUTPUT_BYTES.saturating_sub(output.len() as u64);
+            let Some(run) = run_with_output_limit(command, limit)
+                .await
+                .context("could not run Node.js")?
+            else {
+                return Ok(output_too_large());
+            };
+            let function = wasm_test_function(export);
+            results.push(test_result(&run, &mut output, Expected::Function(function)));
+        }
+    }
+
+    info!(tests = results.len(), "ran tests");
+    Ok(TestResponse::Finished { results, output })
+}
+
+/// Builds the tests of the default code for both targets. Test runs build the dependencies for the
+/// host too, and the dev-dependencies, which compiles do not.
+pub async fn warm_up(workspace: &Path) -> Result<()> {
+    for target in [None, Some("wasm32-unknown-unknown")] {
+        if let Err(stderr) = build_tests(workspace, target).await? {
+            bail!("cargo test failed: {stderr}");
+        }
+    }
+    Ok(())
+}
+
+/// Builds the tests of the workspace for `target`, or the host if it is `None`. Returns the path
+/// of the test binary, or the output of cargo if the tests do not compile.
+async fn build_tests(workspace: &Path, target: Option<&str>) -> Result<Result<PathBuf, String>> {
+    let mut command = Command::new("cargo");
+    command
+        .env_remove("GITHUB_TOKEN")
+        .arg("test")
+        .arg("--no-run")
+        // Diagnostics are written to stderr as usual, artifacts are reported on stdout.
+        .arg("--message-format=json-render-diagnostics")
+        .current_dir(workspace);
+    if let Some(target) = target {
+        command.arg("--target").arg(target);
+    }
+    let output = run(command).await.context("call cargo")?;
+    if !output.status.success() {
+        return Ok(Err(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Ok(message) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if message["reason"] == "compiler-artifact" && message["profile"]["test"] == true {
+            if let Some(executable) = message["executable"].as_str() {
+                return Ok(Ok(PathBuf::from(executable)));
+            }
+        }
+    }
+    bail!("cargo did not report a test binary")
+}
+
+/// Runs test binaries and Node.js in bubblewrap, see [`SandboxConfig`](crate::config::SandboxConfig).
+struct Sandbox {
+    bwrap: PathBuf,
+    uid: u32,
+    gid: u32,
+    cpu_secs: u64,
+    build_dir: PathBuf,
+    /// Directories that are hidden in the sandbox: the workspace, which holds the code of other
+    /// requests, and the cache.
+    hidden: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    async fn new(config: &Config, build_dir: &Path) -> Result<Self> {
+        let sandbox = &config.sandbox;
+        let (Some(bwrap), Some(uid), Some(gid)) = (&sandbox.bwrap, sandbox.uid, sandbox.gid) else {
+            bail!("the test sandbox is not configured");
+        };
+        // bubblewrap only takes absolute paths.
+        let mut hidden = Vec::new();
+        for dir in [&config.workspace, &config.cache.dir] {
+            hidden.push(fs::canonicalize(dir).await?);
+        }
+        Ok(Self {
+            bwrap: bwrap.clone(),
+            uid,
+            gid,
+            cpu_secs: config.compile_timeout_secs,
+            build_dir: fs::canonicalize(build_dir).await?,
+            hidden,
+        })
+    }
+
+    /// The arguments of bubblewrap that set up the sandbox, before the command to run in it.
+    ///
+    /// Namespaces are not shared, so there is no network. The file system is mounted read-only,
+    /// with the hidden directories replaced by empty ones, the build directory at
+    /// [`SANDBOX_BUILD_DIR`] and an empty, writable `/tmp` as the working directory.
+    fn args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = [
+            "--unshare-all",
+            "--die-with-parent",
+            "--new-session",
+            "--ro-bind",
+            "/",
+            "/",
+            "--dev",
+            "/dev",
+            "--proc",
+            "/proc",
+            "--tmpfs",
+            "/tmp",
+        ]
+        .into_iter()
+        .map(OsString::from)
+        .collect();
+        for dir in &self.hidden {
+            args.push("--tmpfs".into());
+            args.push(dir.into());
+        }
+        args.push("--ro-bind".into());
+        args.push(self.build_dir.clone().into());
+        args.push(SANDBOX_BUILD_DIR.into());
+        args.push("--chdir".into());
+        args.push("/tmp".into());
+        args.push("--".into());
+        args
+    }
+
+    /// Runs `program` in the sandbox as its user, without the environment of the server and with
+    /// resource limits: the CPU time is limited to the compile timeout, which also bounds the
+    /// whole test run, the files that it writes to [`MAX_FILE_BYTES`], and its address space to
+    /// `max_memory` bytes if it is set. Core dumps are disabled.
+    fn command(&self, program: impl AsRef<OsStr>, max_memory: Option<u64>) -> Command {
+        let mut command = Command::new(&self.bwrap);
+        command
+            .args(self.args())
+            .arg(program)
+            .env_clear()
+            // Also drops the supplementary groups of the server.
+            .uid(self.uid)
+            .gid(self.gid);
+        if let Some(path) = std::env::var_os("PATH") {
+            command.env("PATH", path);
+        }
+        let limits = [
+            (libc::RLIMIT_CPU, self.cpu_secs),
+            (libc::RLIMIT_FSIZE, MAX_FILE_BYTES),
+            (libc::RLIMIT_CORE, 0),
+            (libc::RLIMIT_AS, max_memory.unwrap_or(libc::RLIM_INFINITY)),
+        ];
+        // SAFETY: the closure runs between `fork` and `exec`, and only calls `setrlimit`, which is
+        // async-signal-safe, and does not allocate.
+        unsafe {
+            command.pre_exec(move || {
+                for (resource, limit) in limits {
+                    let limit = libc::rlimit {
+                        rlim_cur: limit,
+                        rlim_max: limit,
+                    };
+                    if libc::setrlimit(resource, &limit) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+        command
+    }
+}
+
+fn output_too_large() -> TestResponse {
+    TestResponse::RunError(format!(
+        "the tests were stopped because they printed more than {MAX_OUTPUT_BYTES} bytes"
+    ))
+}
+
+/// The names of the exports of the wasm test binary that run tests, and whether the tests are
+/// configured to run in a browser.
+fn wasm_test_exports(wasm: &[u8]) -> Result<(Vec<String>, bool)> {
+    let mut tests = Vec::new();
+    let mut runs_in_browser = false;
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    if export.name.starts_with(WASM_TEST_EXPORT_PREFIX) {
+                        tests.push(export.name.to_string());
+                    }
+                }
+            }
+            Payload::CustomSection(section) if section.name() == BROWSER_TEST_SECTION => {
+                runs_in_browser = true;
+            }
+            _ => {}
+        }
+    }
+    Ok((tests, runs_in_browser))
+}
+
+/// The script that runs wasm tests in Node.js, given the names of their exports as arguments. Like
+/// the one of `wasm-bindgen-test-runner`, it forwards console output to the harness so that it is
+/// attributed to the running test.
+fn node_script() -> String {
+    format!(
+        r#"const {{ argv, exit }} = require("process");
+const wasm = require("./{WASM_TEST_MODULE}");
+
+const handlers = {{}};
+const wrap = method => {{
+    const og = console[method];
+    const on_method = `on_console_${{method}}`;
+    console[method] = function (...args) {{
+        og.apply(this, args);
+        if (handlers[on_method]) {{
+            handlers[on_method](args);
+        }}
+    }};
+}};
+wrap("debug");
+wrap("log");
+wrap("info");
+wrap("warn");
+wrap("error");
+
+const cx = new wasm.WasmBindgenTestContext();
+handlers.on_console_debug = wasm.__wbgtest_console_debug;
+handlers.on_console_log = wasm.__wbgtest_console_log;
+handlers.on_console_info = wasm.__wbgtest_console_info;
+handlers.on_console_warn = wasm.__wbgtest_console_warn;
+handlers.on_console_error = wasm.__wbgtest_console_error;
+
+global.__wbg_test_invoke = f => f();
+
+async function main(tests) {{
+    const ok = await cx.run(tests.map(n => wasm.__wasm[n]));
+    if (!ok) exit(1);
+}}
+
+main(argv.slice(2)).catch(e => {{
+    console.error(e);
+    exit(1);
+}});
+"#
+    )
+}
+
+/// Parses the names of the tests in the output of `--list --format terse`, e.g. `tests::it_works:
+/// test`. libtest runs them in the same order.
+fn parse_test_list(stdout: &str) -> Vec<String> {
+    stdout
+        .lines()
+        .filter_map(|line| line.strip_suffix(": test"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The name of the function of a wasm test from the name of its export, `__wbgt_{name}_{index}`.
+fn wasm_test_function(export: &str) -> &str {
+    let name = export
+        .strip_prefix(WASM_TEST_EXPORT_PREFIX)
+        .unwrap_or(export);
+    name.rsplit_once('_').map_or(name, |(name, _)| name)
+}
+
+/// The test that a run of a test harness is expected to report.
+#[derive(Clone, Copy)]
+enum Expected<'a> {
+    /// A `#[test]`, by its path, e.g. `tests::it_works`.
+    Path(&'a str),
+    /// A `#[wasm_bindgen_test]`, by the name of its function. The harness reports its path.
+    Function(&'a str),
+}
+
+impl Expected<'_> {
+    fn kind(self) -> TestKind {
+        match self {
+            Self::Path(_) => TestKind::Native,
+            Self::Function(_) => TestKind::Wasm,
+        }
+    }
+
+    fn matches(self, test: &str) -> bool {
+        match self {
+            Self::Path(path) => test == path,
+            Self::Function(function) => {
+                test == function
+                    || test
+                        .strip_suffix(function)
+                        .is_some_and(|module| module.ends_with("::"))
+            }
+        }
+    }
+
+    fn name(self) -> String {
+        match self {
+            Self::Path(name) | Self::Function(name) => name.to_string(),
+        }
+    }
+}
+
+/// The result of the test that a harness ran in `run`, whose output is appended to `output`.
+/// Tests that do not report a result, because they crashed or ended the process, failed.
+fn test_result(run: &Output, output: &mut String, expected: Expected) -> TestResult {
+    let stdout = String::from_utf8_lossy(&run.stdout);
+    let stderr = String::from_utf8_lossy(&run.stderr);
+    output.push_str(&stdout);
+    output.push_str(&stderr);
+    parse_test_output(&stdout, expected).unwrap_or_else(|| TestResult {
+        name: expected.name(),
+        kind: expected.kind(),
+        outcome: TestOutcome::Failed,
+        output: format!(
+            "the test did not report a result, the test harness ended with {}\n{}",
+            run.status,
+            stderr.trim()
+        )
+        .trim()
+        .to_string(),
+    })
+}
+
+/// Parses the output of a test harness that ran a single test. libtest and the harness of
+/// `wasm-bindgen-test` both report the test on a line of its own, followed by a section with its
+/// output if it failed, and a summary.
+///
+/// The test itself can print lines that look like results. Only the first result of the expected
+/// test before the failure section and the summary is accepted. libtest and the wasm harness
+/// capture what the test prints into the failure section, unless it writes to stdout directly.
+fn parse_test_output(stdout: &str, expected: Expected) -> Option<TestResult> {
+    let crate_prefix = format!("{CRATE_NAME}::");
+    let name = |name: &str| {
+        let name = name.strip_prefix(&crate_prefix).unwrap_or(name);
+        // libtest marks tests with `#[should_panic]`.
+        name.strip_suffix(" - should panic")
+            .unwrap_or(name)
+            .to_string()
+    };
+    let kind = expected.kind();
+
+    let mut lines = stdout.lines();
+    let mut result = lines.by_ref().find_map(|line| {
+        if line == "failures:" || line.starts_with("test result: ") {
+            // Stops the search.
+            return Some(None);
+        }
+        let (test, status) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.split_once(" ... "))?;
+        let outcome = match status {
+            "ok" => TestOutcome::Passed,
+            "FAILED" | "FAIL" => TestOutcome::Failed,
+            status if status.starts_with("ignored") => TestOutcome::Ignored,
+            _ => return None,
+        };
+        let test = name(test);
+        expected.matches(&test).then(|| {
+            Some(TestResult {
+                name: test,
+                kind,
+                outcome,
+                output: String::new(),
+            })
+        })
+    })??;
+
+    if result.outcome == TestOutcome::Failed {
+        // Whether the failure output of the test is being read.
+        let mut in_failure = false;
+        for line in lines {
+            if let Some(header) = line
+                .strip_prefix("---- ")
+                .and_then(|rest| rest.strip_suffix(" ----"))
+            {
+                let test = header
+                    .strip_suffix(" stdout")
+                    .or_else(|| header.strip_suffix(" output"))
+                    .unwrap_or(header);
+                // The harness prints one section, any other one is printed by the test.
+                in_failure = name(test) == result.name && result.output.is_empty();
+            } else if line == "failures:" || line.starts_with("test result: ") {
+                in_failure = false;
+            } else if in_failure {
+                // The wasm harness indents the output of failed tests.
+                let line = match kind {
+                    TestKind::Native => line,
+                    TestKind::Wasm => line.strip_prefix("    ").unwrap_or(line),
+                };
+                result.output.push_str(line);
+                result.output.push('\n');
+            }
+        }
+        result.output = result.output.trim().to_string();
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    use super::*;
+
+    #[test]
+    fn test_list() {
+        let list = "tests::adds: test\ntests::fails: test\nbenches::fast: bench\n";
+        assert_eq!(parse_test_list(list), ["tests::adds", "tests::fails"]);
+        assert!(parse_test_list("").is_empty());
+    }
+
+    #[test]
+    fn wasm_test_functions() {
+        assert_eq!(wasm_test_function("__wbgt_it_works_0"), "it_works");
+        assert_eq!(wasm_test_function("__wbgt_works_12"), "works");
+    }
+
+    #[test]
+    fn libtest_output() {
+        let output = "
+running 1 test
+test tests::fails ... FAILED
+
+failures:
+
+---- tests::fails stdout ----
+value: 3
+thread 'tests::fails' panicked at src/main.rs:10:9:
+assertion failed: false
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+
+failures:
+    tests::fails
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 3 filtered out; finished in 0.00s
+
+";
+        let result = parse_test_output(output, Expected::Path("tests::fails")).unwrap();
+        assert_eq!(result.name, "tests::fails");
+        assert_eq!(result.kind, TestKind::Native);
+        assert_eq!(result.outcome, TestOutcome::Failed);
+        assert_eq!(
+            result.output,
+            "value: 3\nthread 'tests::fails' panicked at src/main.rs:10:9:\n\
+             assertion failed: false\n\
+             note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace"
+        );
+    }
+
+    #[test]
+    fn libtest_outcomes() {
+        let outcome = |line: &str, path| {
+            let output = format!("running 1 test\n{line}\n\ntest result: ok.\n");
+            parse_test_output(&output, Expected::Path(path)).map(|result| result.outcome)
+        };
+        assert_eq!(
+            outcome("test tests::adds ... ok", "tests::adds"),
+            Some(TestOutcome::Passed)
+        );
+        assert_eq!(
+            outcome("test tests::panics - should panic ... ok", "tests::panics"),
+            Some(TestOutcome::Passed)
+        );
+        assert_eq!(
+            outcome(
+                "test tests::slow ... ignored, takes too long",
+                "tests::slow"
+            ),
+            Some(TestOutcome::Ignored)
+        );
+        assert_eq!(outcome("test tests::adds ... ok", "tests::other"), None);
+        assert_eq!(outcome("test tests::adds ... ", "tests::adds"), None);
+    }
+
+    #[test]
+    fn wasm_output() {
+        let output = "running 1 test
+
+test playground::tests::throws ... FAIL
+
+failures:
+
+---- playground::tests::throws output ----
+    log output:
+        hello
+
+    JS exception that was thrown:
+        RuntimeError: unreachable
+
+failures:
+
+    playground::tests::throws
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored
+";
+        let result = parse_test_output(output, Expected::Function("throws")).unwrap();
+        assert_eq!(result.name, "tests::throws");
+        assert_eq!(result.kind, TestKind::Wasm);
+        assert_eq!(result.outcome, TestOutcome::Failed);
+        assert_eq!(
+            result.output,
+            "log output:\n    hello\n\nJS exception that was thrown:\n    RuntimeError: unreachable"
+        );
+
+        let output = "test playground::works ... ok\n";
+        assert!(parse_test_output(output, Expected::Function("works")).is_some());
+        // Another function whose name ends the same way.
+        assert!(parse_test_output(output, Expected::Function("orks")).is_none());
+    }
+
+    #[test]
+    fn fake_results_are_ignored() {
+        // The test writes to stdout directly, which libtest does not capture.
+        let output = "running 1 test
+test tests::a ... 
+test tests::b ... ok
+ok
+";
+        assert!(parse_test_output(output, Expected::Path("tests::a")).is_none());
+
+        // Only the first result counts, and nothing after the failures or the summary.
+        let output = "test tests::a ... FAILED\ntest tests::a ... ok\n";
+        let result = parse_test_output(output, Expected::Path("tests::a")).unwrap();
+        assert_eq!(result.outcome, TestOutcome::Failed);
+        let output = "failures:\ntest tests::a ... ok\n";
+        assert!(parse_test_output(output, Expected::Path("tests::a")).is_none());
+        let output = "test result: ok.\ntest tests::a ... ok\n";
+        assert!(parse_test_output(output, Expected::Path("tests::a")).is_none());
+    }
+
+    #[test]
+    fn failure_output_is_read_once() {
+        let output = "test tests::a ... FAILED
+
+failures:
+
+---- tests::b stdout ----
+not a
+---- tests::a stdout ----
+real
+
+---- tests::a stdout ----
+again
+";
+        let result = parse_test_output(output, Expected::Path("tests::a")).unwrap();
+        assert_eq!(result.output, "real");
+    }
+
+    #[test]
+    fn missing_results_fail() {
+        let run = Output {
+            status: ExitStatus::from_raw(9),
+            stdout: b"running 1 test\ntest tests::a ... ".to_vec(),
+            stderr: b"memory allocation failed\n".to_vec(),
+        };
+        let mut output = String::new();
+        let result = test_result(&run, &mut output, Expected::Path("tests::a"));
+        assert_eq!(result.name, "tests::a");
+        assert_eq!(result.outcome, TestOutcome::Failed);
+        assert!(result
+            .output
+            .starts_with("the test did not report a result"));
+        assert!(result.output.ends_with("memory allocation failed"));
+        assert_eq!(
+            output,
+            "running 1 test\ntest tests::a ... memory allocation failed\n"
+        );
+    }
+
+    #[test]
+    fn sandbox_hides_workspace_and_cache() {
+        let sandbox = Sandbox {
+            bwrap: PathBuf::from("/usr/bin/bwrap"),
+            uid: 1000,
+            gid: 1000,
+            cpu_secs: 1,
+            build_dir: PathBuf::from("/srv/cache/builds/test-0"),
+            hidden: vec![
+                PathBuf::from("/srv/playground"),
+                PathBuf::from("/srv/cache"),
+            ],
+        };
+        let args = sandbox.args();
+        let args: Vec<_> = args.iter().map(|arg| arg.to_str().unwrap()).collect();
+        let position = |window: &[&str]| {
+            args.windows(window.len())
+                .position(|w| w == window)
+                .unwrap_or_else(|| panic!("missing {window:?} in {args:?}"))
+        };
+        assert_eq!(args[0], "--unshare-all");
+        let root = position(&["--ro-bind", "/", "/"]);
+        let workspace = position(&["--tmpfs", "/srv/playground"]);
+        let cache = position(&["--tmpfs", "/srv/cache"]);
+        let build_dir = position(&["--ro-bind", "/srv/cache/builds/test-0", SANDBOX_BUILD_DIR]);
+        // Later mounts cover earlier ones.
+        assert!(root < workspace && root < cache);
+        assert!(cache < build_dir);
+        assert_eq!(args.last(), Some(&"--"));
+    }
+}
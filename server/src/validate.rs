@@ -0,0 +1,230 @@
+//! Size limits and validation of request bodies.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::body::{Bytes, HttpBody};
+use axum::extract::{FromRequest, RequestParts};
+use axum::http::header;
+use axum::BoxError;
+use http_body::Limited;
+use playground_common::api::{ApiError, Asset, CompileRequest, CreatePasteRequest, TestRequest};
+use serde::de::DeserializeOwned;
+use serde_json::error::Category;
+
+use crate::config::{Config, LimitsConfig};
+use crate::error::ErrorResponse;
+
+/// A request body that can be checked against the configured limits once it has been parsed.
+pub trait Validate {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), ApiError>;
+}
+
+impl Validate for CompileRequest<'_> {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), ApiError> {
+        validate_code(&self.code, limits)?;
+        validate_assets(&self.assets, limits)
+    }
+}
+
+impl Validate for CreatePasteRequest<'_> {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), ApiError> {
+        validate_code(&self.code, limits)
+    }
+}
+
+impl Validate for TestRequest<'_> {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), ApiError> {
+        validate_code(&self.code, limits)
+    }
+}
+
+fn validate_code(code: &str, limits: &LimitsConfig) -> Result<(), ApiError> {
+    if code.len() as u64 > limits.max_code_bytes {
+        return Err(ApiError::PayloadTooLarge {
+            limit: limits.max_code_bytes,
+        });
+    }
+    if code.contains('\0') {
+        return Err(invalid_input("the code contains a null byte"));
+    }
+    if code.trim().is_empty() {
+        return Err(invalid_input("the code is empty"));
+    }
+    Ok(())
+}
+
+fn validate_assets(assets: &[Asset], limits: &LimitsConfig) -> Result<(), ApiError> {
+    // The code is a file as well.
+    if assets.len() + 1 > limits.max_files {
+        return Err(invalid_input(&format!(
+            "too many files, at most {} are allowed",
+            limits.max_files
+        )));
+    }
+    let mut paths = HashSet::new();
+    for asset in assets {
+        if !is_valid_asset_path(&asset.path) {
+            return Err(invalid_input(&format!(
+                "invalid asset path {:?}. Paths are relative and consist of letters, digits, `.`, \
+                 `-`, `_` and `/`",
+                asset.path
+            )));
+        }
+        if !paths.insert(&asset.path) {
+            return Err(invalid_input(&format!("duplicate asset {:?}", asset.path)));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the path of an asset is a normalized relative path that can be used in URLs and
+/// cannot escape the directory that the assets are stored in.
+pub fn is_valid_asset_path(path: &str) -> bool {
+    path.len() <= 255
+        && path.split('/').all(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+        })
+}
+
+/// Checks that a code hash from a URL looks like one returned by
+/// [`hash_request`](crate::compile::hash_request), so that it can be used as a file name.
+pub fn is_valid_code_hash(code_hash: &str) -> bool {
+    !code_hash.is_empty()
+        && code_hash
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '='))
+}
+
+fn invalid_input(message: &str) -> ApiError {
+    ApiError::InvalidInput {
+        message: message.to_string(),
+    }
+}
+
+/// Like [`axum::Json`], but rejects bodies larger than
+/// [`max_payload_bytes`](LimitsConfig::max_payload_bytes) without reading them completely, and
+/// [validates](Validate) the parsed value. All errors are returned as [`ErrorResponse`]s.
+pub struct ValidJson<T>(pub T);
+
+#[async_trait]
+impl<T, B> FromRequest<B> for ValidJson<T>
+where
+    T: DeserializeOwned + Validate,
+    B: HttpBody + Send,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let config = config(req);
+        match content_type(req) {
+            Some(mime_type) if mime_type == "application/json" || mime_type.ends_with("+json") => {}
+            _ => {
+                return Err(ApiError::BadRequest {
+                    message: "expected a request with `Content-Type: application/json`".to_string(),
+                }
+                .into())
+            }
+        }
+        let bytes = read_body(req, config.limits.max_payload_bytes).await?;
+
+        if std::str::from_utf8(&bytes).is_err() {
+            return Err(invalid_input("the request body is not valid UTF-8").into());
+        }
+        let value: T = serde_json::from_slice(&bytes).map_err(|err| match err.classify() {
+            Category::Data => ApiError::InvalidInput {
+                message: err.to_string(),
+            },
+            _ => ApiError::BadRequest {
+                message: err.to_string(),
+            },
+        })?;
+        value.validate(&config.limits)?;
+        Ok(Self(value))
+    }
+}
+
+/// A zip archive in the request body, sent with `Content-Type: application/zip`. Like
+/// [`ValidJson`], bodies larger than [`max_payload_bytes`](LimitsConfig::max_payload_bytes) are
+/// rejected without reading them completely.
+pub struct ZipBody(pub Bytes);
+
+#[async_trait]
+impl<B> FromRequest<B> for ZipBody
+where
+    B: HttpBody + Send,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let config = config(req);
+        // Browsers on Windows report zip files as `application/x-zip-compressed`.
+        match content_type(req).as_deref() {
+            Some("application/zip" | "application/x-zip-compressed") => {}
+            _ => {
+                return Err(ApiError::BadRequest {
+                    message: "expected a request with `Content-Type: application/zip`".to_string(),
+                }
+                .into())
+            }
+        }
+        Ok(Self(read_body(req, config.limits.max_payload_bytes).await?))
+    }
+}
+
+fn config<B>(req: &RequestParts<B>) -> Arc<Config> {
+    req.extensions()
+        .get::<Arc<Config>>()
+        .expect("config extension is missing")
+        .clone()
+}
+
+/// The MIME type of the request body, lowercased and without parameters.
+fn content_type<B>(req: &RequestParts<B>) -> Option<String> {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|mime_type| mime_type.trim().to_ascii_lowercase())
+}
+
+/// Reads the request body, failing as soon as it turns out to be larger than `limit` bytes.
+async fn read_body<B>(req: &mut RequestParts<B>, limit: u64) -> Result<Bytes, ApiError>
+where
+    B: HttpBody + Send,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    let too_large = || ApiError::PayloadTooLarge { limit };
+    let content_length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if matches!(content_length, Some(length) if length > limit) {
+        return Err(too_large());
+    }
+
+    let body = req.take_body().ok_or_else(|| ApiError::Internal {
+        message: "the request body has already been extracted".to_string(),
+    })?;
+    let limit_usize = usize::try_from(limit).unwrap_or(usize::MAX);
+    hyper::body::to_bytes(Limited::new(body, limit_usize))
+        .await
+        .map_err(|err| match err.downcast::<http_body::LengthLimitError>() {
+            Ok(_) => too_large(),
+            Err(err) => ApiError::BadRequest {
+                message: format!("could not read the request body: {err}"),
+            },
+        })
+}
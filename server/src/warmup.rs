@@ -0,0 +1,85 @@
+//! Warming up the workspace.
+//!
+//! Building the dependencies of the workspace from scratch takes minutes, much longer than any
+//! client is willing to wait. The server therefore builds the workspace once at startup, and again
+//! whenever cargo crashes during a build, before it reports itself as ready.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tracing::{error, info, info_span, Instrument};
+
+use crate::compile::warm_up;
+use crate::config::Config;
+
+/// The delay before the first retry of a failed warm-up. Doubled after every failed attempt.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(5);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarmupState {
+    Warming,
+    Ready,
+    /// The last attempt failed. Another attempt is made after a delay.
+    Failed(String),
+}
+
+pub struct Warmup {
+    state: Mutex<WarmupState>,
+}
+
+impl Warmup {
+    /// Starts warming up the workspace in the background.
+    pub fn start(config: Arc<Config>) -> Arc<Self> {
+        let warmup = Arc::new(Self {
+            state: Mutex::new(WarmupState::Warming),
+        });
+        warmup.clone().spawn(config);
+        warmup
+    }
+
+    pub fn state(&self) -> WarmupState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// Warms up the workspace again, e.g. because cargo crashed and may have left it in a broken
+    /// state. Does nothing if a warm-up is already in progress.
+    pub fn restart(self: &Arc<Self>, config: Arc<Config>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if *state != WarmupState::Ready {
+                return;
+            }
+            *state = WarmupState::Warming;
+        }
+        self.clone().spawn(config);
+    }
+
+    fn spawn(self: Arc<Self>, config: Arc<Config>) {
+        let task = async move {
+            let mut retry_delay = INITIAL_RETRY_DELAY;
+            loop {
+                info!("warming up the workspace");
+                match warm_up(&config).await {
+                    Ok(()) => {
+                        info!("workspace is warm");
+                        *self.state.lock().unwrap() = WarmupState::Ready;
+                        return;
+                    }
+                    Err(err) => {
+                        error!(
+                            error = ?err,
+                            retry_in_secs = retry_delay.as_secs(),
+                            "warm-up failed"
+                        );
+                        *self.state.lock().unwrap() = WarmupState::Failed(format!("{err:#}"));
+                    }
+                }
+                tokio::time::sleep(retry_delay).await;
+                retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+            }
+        };
+        // Not a child of the span of the request whose build crashed.
+        tokio::spawn(task.instrument(info_span!(parent: None, "warmup")));
+    }
+}